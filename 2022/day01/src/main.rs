@@ -1,44 +1,191 @@
-use std::cmp::max;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::path::Path;
 use std::io::{BufRead, BufReader};
-use std::ops::{Add, Sub, Mul, Rem, Div};
+use std::ops::RangeInclusive;
 
-fn calc_new_max_sums<T: PartialEq + Ord + Add + Sub + Mul + Rem + Div + Copy>(max_sums: &mut Vec<T>, new_sum: T) {
-	max_sums[0] = max(max_sums[0], new_sum);
-	if max_sums[1] < max_sums[0] {
-		max_sums.swap(1, 0);
+// Ordered only by value, so the source does not need to be Ord
+#[derive(Debug)]
+struct Entry<T, S> {
+	value: T,
+	source: S,
+}
+
+impl<T: Ord, S> PartialEq for Entry<T, S> {
+	fn eq(&self, other: &Self) -> bool {
+		self.value == other.value
+	}
+}
+
+impl<T: Ord, S> Eq for Entry<T, S> {}
+
+impl<T: Ord, S> PartialOrd for Entry<T, S> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<T: Ord, S> Ord for Entry<T, S> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.value.cmp(&other.value)
+	}
+}
+
+/// Streaming aggregator keeping the `k` largest values pushed into it, along with where each came from.
+struct TopK<T, S> {
+	k: usize,
+	// Min heap, so the smallest kept value is the one replaced
+	heap: BinaryHeap<Reverse<Entry<T, S>>>,
+}
+
+impl<T: Ord, S> TopK<T, S> {
+	fn new(k: usize) -> Self {
+		TopK { k, heap: BinaryHeap::with_capacity(k + 1) }
 	}
-	if max_sums[2] < max_sums[1] {
-		max_sums.swap(2, 1);
+
+	fn push(&mut self, value: T, source: S) {
+		if self.k == 0 {
+			return;
+		}
+
+		if self.heap.len() < self.k {
+			self.heap.push(Reverse(Entry { value, source }));
+		} else if self.heap.peek().is_some_and(|Reverse(min)| min.value < value) {
+			self.heap.pop();
+			self.heap.push(Reverse(Entry { value, source }));
+		}
+	}
+
+	/// Kept values with their sources, largest first.
+	fn into_sorted_vec(self) -> Vec<(T, S)> {
+		// Sorting Reverse ascending orders the values descending
+		self.heap
+			.into_sorted_vec()
+			.into_iter()
+			.map(|Reverse(entry)| (entry.value, entry.source))
+			.collect()
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Elf {
+	index: usize,
+	lines: RangeInclusive<usize>, // 1 based line numbers of the elf's calories
+}
+
+fn find_top_elves(reader: impl BufRead, k: usize) -> Result<Vec<(u32, Elf)>, String> {
+	let mut top = TopK::new(k);
+	let mut curr_sum: u32 = 0;
+	let mut curr_start = None;
+	let mut elf_index = 0;
+
+	let mut finish_elf = |sum: u32, start: Option<usize>, end: usize| {
+		if let Some(start) = start {
+			top.push(sum, Elf { index: elf_index, lines: start..=end });
+			elf_index += 1;
+		}
+	};
+
+	let mut line_number = 0;
+	for line in reader.lines() {
+		let text = line.map_err(|err| err.to_string())?;
+		line_number += 1;
+
+		if text.is_empty() || text.starts_with(char::is_whitespace) {
+			finish_elf(curr_sum, curr_start.take(), line_number - 1);
+			curr_sum = 0;
+		} else {
+			let calories = text
+				.parse::<u32>()
+				.map_err(|err| format!("Unable to parse line {line_number} to unsigned int: {err}"))?;
+			curr_sum += calories;
+			curr_start.get_or_insert(line_number);
+		}
 	}
+	// Input might not end with an empty line
+	finish_elf(curr_sum, curr_start, line_number);
+
+	Ok(top.into_sorted_vec())
 }
 
 fn main() {
+	let k = match std::env::args().nth(1) {
+		Some(arg) => arg.parse::<usize>().expect("Argument should be the number of elves to report on"),
+		None => 3,
+	};
 	let input_path = Path::new("input_a.txt");
 	let input_file = File::open(input_path).expect("Could not open file input_a.txt");
 	let reader = BufReader::new(input_file);
-	let mut curr_sum: u32 = 0;
-	let mut max_sums = vec![0, 0, 0];
 
-	for line in reader.lines() {
-		match line {
-			Ok(text) => {
-				if text.is_empty() || char::is_whitespace(text.as_str().chars().nth(0).unwrap()) {
-					calc_new_max_sums(&mut max_sums, curr_sum);
-					curr_sum = 0;
-				} else {
-					curr_sum += text.parse::<u32>().expect("Unable to parse value to unsigned int");
-				}
-			}
-			Err(err) => {
-				panic!("{err}");
-			}
+	let top_elves = find_top_elves(reader, k).unwrap_or_else(|err| panic!("{err}"));
+
+	for (sum, elf) in top_elves.iter() {
+		println!("Elf {} (lines {}-{}): {sum}", elf.index, elf.lines.start(), elf.lines.end());
+	}
+	println!("Sum: {}", top_elves.iter().map(|(sum, _)| sum).sum::<u32>());
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	static TEST_INPUT: &str = "1000
+2000
+3000
+
+4000
+
+5000
+6000
+
+7000
+8000
+9000
+
+10000";
+
+	#[test]
+	fn test_top_k() {
+		let mut top = TopK::new(3);
+		for (index, value) in [5, 1, 9, 3, 9, 7, 2].into_iter().enumerate() {
+			top.push(value, index);
 		}
+
+		let values: Vec<i32> = top.into_sorted_vec().into_iter().map(|(value, _)| value).collect();
+		assert_eq!(vec![9, 9, 7], values);
+	}
+
+	#[test]
+	fn test_top_k_edge_sizes() {
+		let mut top = TopK::new(0);
+		top.push("a", ());
+		assert!(top.into_sorted_vec().is_empty());
+
+		let mut top = TopK::new(10);
+		top.push("b", ());
+		top.push("a", ());
+		let values: Vec<&str> = top.into_sorted_vec().into_iter().map(|(value, _)| value).collect();
+		assert_eq!(vec!["b", "a"], values);
+	}
+
+	#[test]
+	fn test_first_half() {
+		let top = find_top_elves(TEST_INPUT.as_bytes(), 1).unwrap();
+		assert_eq!(vec![(24_000, Elf { index: 3, lines: 10..=12 })], top);
+	}
+
+	#[test]
+	fn test_second_half() {
+		let top = find_top_elves(TEST_INPUT.as_bytes(), 3).unwrap();
+
+		assert_eq!(45_000, top.iter().map(|(sum, _)| sum).sum::<u32>());
+		assert_eq!(vec![3, 2, 4], top.iter().map(|(_, elf)| elf.index).collect::<Vec<usize>>());
+		assert_eq!(14..=14, top[2].1.lines);
 	}
 
-	for sum in max_sums.iter() {
-		print!("{sum} ")
+	#[test]
+	fn test_parse_error() {
+		assert!(find_top_elves("100\nabc\n".as_bytes(), 3).is_err());
 	}
-	print!("\nSum: {}\n", max_sums.iter().sum::<u32>());
 }
@@ -1,54 +1,321 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Outcome {
+	Loss,
+	Draw,
+	Win,
+}
+
+/// A cyclic hand game with an odd number of moves, listed in an order where each move beats the
+/// (n - 1) / 2 moves before it, wrapping around. Rock, paper, scissors is the game with 3 moves.
+#[derive(Debug, Clone)]
+struct Game {
+	names: Vec<String>,
+	hand_scores: Vec<i32>,
+	outcome_scores: HashMap<Outcome, i32>,
+	opponent_letters: HashMap<char, usize>,
+	own_letters: HashMap<char, usize>,
+	outcome_letters: HashMap<char, Outcome>,
+}
+
+fn check_distinct(letters: impl Iterator<Item = char>) -> Result<(), String> {
+	match letters.duplicates().next() {
+		Some(letter) => Err(format!("Letter {letter} is used more than once")),
+		None => Ok(()),
+	}
+}
+
+impl Game {
+	/// `moves` are (name, hand score) pairs in cycle order, the letters encode the moves in the same order.
+	/// Outcomes always score 0 for a loss, 3 for a draw and 6 for a win, as in the puzzle.
+	fn new(moves: &[(&str, i32)], opponent_letters: &str, own_letters: &str, outcome_letters: [char; 3]) -> Result<Self, String> {
+		if moves.len().is_multiple_of(2) {
+			return Err(format!("A cyclic game needs an odd number of moves, got {}", moves.len()));
+		}
+
+		let encode = |letters: &str| -> Result<HashMap<char, usize>, String> {
+			if letters.chars().count() != moves.len() {
+				return Err(format!("Letters {letters} don't encode {} moves", moves.len()));
+			}
+			check_distinct(letters.chars())?;
+			Ok(letters.chars().enumerate().map(|(i, c)| (c, i)).collect())
+		};
+		check_distinct(outcome_letters.into_iter())?;
+
+		Ok(Game {
+			names: moves.iter().map(|(name, _)| name.to_string()).collect(),
+			hand_scores: moves.iter().map(|(_, score)| *score).collect(),
+			outcome_scores: HashMap::from([(Outcome::Loss, 0), (Outcome::Draw, 3), (Outcome::Win, 6)]),
+			opponent_letters: encode(opponent_letters)?,
+			own_letters: encode(own_letters)?,
+			outcome_letters: HashMap::from([
+				(outcome_letters[0], Outcome::Loss),
+				(outcome_letters[1], Outcome::Draw),
+				(outcome_letters[2], Outcome::Win),
+			]),
+		})
+	}
+
+	fn rock_paper_scissors() -> Self {
+		Game::new(&[("Rock", 1), ("Paper", 2), ("Scissors", 3)], "ABC", "XYZ", ['X', 'Y', 'Z'])
+			.expect("Rock, paper, scissors should be a valid game")
+	}
+
+	fn move_count(&self) -> usize {
+		self.names.len()
+	}
+
+	fn outcome(&self, my: usize, opp: usize) -> Outcome {
+		let n = self.move_count();
+		let distance = (my + n - opp) % n;
+
+		if distance == 0 {
+			Outcome::Draw
+		} else if distance <= (n - 1) / 2 {
+			Outcome::Win
+		} else {
+			Outcome::Loss
+		}
+	}
+
+	/// Move giving the wanted outcome against the opponent's move. With more than 3 moves several
+	/// could, the one right after (or before) the opponent's in the cycle is picked.
+	fn move_for_outcome(&self, opp: usize, outcome: Outcome) -> usize {
+		let n = self.move_count();
+
+		match outcome {
+			Outcome::Draw => opp,
+			Outcome::Win => (opp + 1) % n,
+			Outcome::Loss => (opp + n - 1) % n,
+		}
+	}
+
+	fn score(&self, my: usize, opp: usize) -> i32 {
+		self.hand_scores[my] + self.outcome_scores[&self.outcome(my, opp)]
+	}
+
+	fn opponent_move(&self, opp: char) -> Result<usize, String> {
+		self.opponent_letters.get(&opp).copied().ok_or(format!("Invalid char as opponent move {opp}"))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecodingKind {
+	Moves,
+	Outcomes,
+}
+
+/// One way of reading the second column of the strategy guide, and the score it gives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decoding {
+	kind: DecodingKind,
+	assignments: Vec<(char, String)>,
+	score: i32,
+}
+
+impl std::fmt::Display for Decoding {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let assignments = self.assignments.iter().map(|(letter, meaning)| format!("{letter}={meaning}")).join(" ");
+		write!(f, "{assignments} as {:?}: {}", self.kind, self.score)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DecoderReport {
+	decodings: Vec<Decoding>,
+	best: Decoding,
+	worst: Decoding,
+	// Mean over all decodings, each considered equally likely
+	expected: f64,
+}
+
+fn parse_guide(input: &str) -> Vec<(char, char)> {
+	input
+		.lines()
+		.filter(|line| line.len() > 2)
+		.map(|line| (line.chars().next().unwrap(), line.chars().nth(2).unwrap()))
+		.collect()
+}
+
+fn total_score(game: &Game, guide: &[(char, char)], score: fn(&Game, char, char) -> Result<i32, String>) -> Result<i32, String> {
+	guide.iter().map(|&(opp, my)| score(game, opp, my)).sum()
+}
+
+/// Scores every mapping of the guide's second column to moves, and to outcomes.
+fn decode_strategy_guide(game: &Game, guide: &[(char, char)]) -> Result<DecoderReport, String> {
+	let mut decodings = vec![];
+
+	let own_letters: Vec<char> = game.own_letters.iter().sorted_by_key(|(_, &i)| i).map(|(&c, _)| c).collect();
+	for moves in (0..game.move_count()).permutations(game.move_count()) {
+		let mut decoded = game.clone();
+		decoded.own_letters = own_letters.iter().copied().zip(moves.iter().copied()).collect();
+
+		decodings.push(Decoding {
+			kind: DecodingKind::Moves,
+			assignments: own_letters.iter().zip(&moves).map(|(&c, &m)| (c, game.names[m].clone())).collect(),
+			score: total_score(&decoded, guide, calc_old_score)?,
+		});
+	}
+
+	let outcome_letters: Vec<char> = game.outcome_letters.keys().copied().sorted().collect();
+	for outcomes in [Outcome::Loss, Outcome::Draw, Outcome::Win].into_iter().permutations(3) {
+		let mut decoded = game.clone();
+		decoded.outcome_letters = outcome_letters.iter().copied().zip(outcomes.iter().copied()).collect();
+
+		decodings.push(Decoding {
+			kind: DecodingKind::Outcomes,
+			assignments: outcome_letters.iter().zip(&outcomes).map(|(&c, o)| (c, format!("{o:?}"))).collect(),
+			score: total_score(&decoded, guide, calc_new_score)?,
+		});
+	}
+
+	let best = decodings.iter().max_by_key(|d| d.score).unwrap().clone();
+	let worst = decodings.iter().min_by_key(|d| d.score).unwrap().clone();
+	let expected = decodings.iter().map(|d| d.score as f64).sum::<f64>() / decodings.len() as f64;
+
+	Ok(DecoderReport { decodings, best, worst, expected })
+}
+
 fn main() {
 	let input = include_str!("input.txt");
-	let mut old_score = 0;
-	let mut new_score = 0;
+	let game = Game::rock_paper_scissors();
+	let guide = parse_guide(input);
 
-	for line in input.lines() {
-		if line.len() > 2 {
-			let opponent_move = line.chars().nth(0).unwrap();
-			let my_move = line.chars().nth(2).unwrap();
-
-			old_score += calc_old_score(opponent_move, my_move).unwrap();
-			new_score += calc_new_score(opponent_move, my_move).unwrap();
+	if std::env::args().any(|arg| arg == "--decode") {
+		let report = decode_strategy_guide(&game, &guide).unwrap();
+		for decoding in &report.decodings {
+			println!("{decoding}");
 		}
+		println!("\nBest: {}", report.best);
+		println!("Worst: {}", report.worst);
+		println!("Expected: {:.1}", report.expected);
+		return;
 	}
 
+	let old_score = total_score(&game, &guide, calc_old_score).unwrap();
+	let new_score = total_score(&game, &guide, calc_new_score).unwrap();
+
 	println!("Old score: {old_score}");
 	println!("New score: {new_score}");
 }
 
-fn calc_old_score(opp: char, my: char) -> Result<i32, String> {
-	let hand_score = match my {
-		'X' => 1,
-		'Y' => 2,
-		'Z' => 3,
-		_ => return Err(format!("Invalid char as own move {my}")),
-	};
-
-	let outcome_score = match (opp, my) {
-		('A', 'X') => 3,
-		('A', 'Y') => 6,
-		('A', 'Z') => 0,
-		('B', 'X') => 0,
-		('B', 'Y') => 3,
-		('B', 'Z') => 6,
-		('C', 'X') => 6,
-		('C', 'Y') => 0,
-		('C', 'Z') => 3,
-		_ => return Err(format!("Invalid char/s as moves ({opp}, {my})")),
-	};
-
-	Ok(hand_score + outcome_score)
-}
-
-fn calc_new_score(opp: char, tactic: char) -> Result<i32, String> {
-	let my_move: char = match (opp, tactic) {
-		// (A, X) means my opponent uses rock and I must lose, ergo use Z for scissors
-		('A', 'X') | ('B', 'Z') | ('C', 'Y') => 'Z',
-		('A', 'Y') | ('B', 'X') | ('C', 'Z') => 'X',
-		('A', 'Z') | ('B', 'Y') | ('C', 'X') => 'Y',
-		_ => return Err(format!("Invalid opponent move or my tactic ({opp}, {tactic})")),
-	};
-
-	calc_old_score(opp, my_move)
-}
\ No newline at end of file
+fn calc_old_score(game: &Game, opp: char, my: char) -> Result<i32, String> {
+	let opp = game.opponent_move(opp)?;
+	let my = game.own_letters.get(&my).copied().ok_or(format!("Invalid char as own move {my}"))?;
+
+	Ok(game.score(my, opp))
+}
+
+fn calc_new_score(game: &Game, opp: char, tactic: char) -> Result<i32, String> {
+	let opp = game.opponent_move(opp)?;
+	let outcome = game.outcome_letters.get(&tactic).copied().ok_or(format!("Invalid char as tactic {tactic}"))?;
+
+	Ok(game.score(game.move_for_outcome(opp, outcome), opp))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	static TEST_INPUT: &str = "A Y
+B X
+C Z";
+
+	fn total(game: &Game, input: &str, score: fn(&Game, char, char) -> Result<i32, String>) -> i32 {
+		total_score(game, &parse_guide(input), score).unwrap()
+	}
+
+	fn rock_paper_scissors_lizard_spock() -> Game {
+		let moves = [("Rock", 1), ("Spock", 2), ("Paper", 3), ("Lizard", 4), ("Scissors", 5)];
+		Game::new(&moves, "ABCDE", "VWXYZ", ['X', 'Y', 'Z']).unwrap()
+	}
+
+	#[test]
+	fn test_first_half() {
+		assert_eq!(15, total(&Game::rock_paper_scissors(), TEST_INPUT, calc_old_score));
+	}
+
+	#[test]
+	fn test_second_half() {
+		assert_eq!(12, total(&Game::rock_paper_scissors(), TEST_INPUT, calc_new_score));
+	}
+
+	#[test]
+	fn test_matches_hand_written_rules() {
+		let game = Game::rock_paper_scissors();
+		let expected = [('A', 'X', 4), ('A', 'Y', 8), ('A', 'Z', 3), ('B', 'X', 1), ('B', 'Y', 5), ('B', 'Z', 9), ('C', 'X', 7), ('C', 'Y', 2), ('C', 'Z', 6)];
+
+		for (opp, my, score) in expected {
+			assert_eq!(Ok(score), calc_old_score(&game, opp, my));
+		}
+		assert!(calc_old_score(&game, 'D', 'X').is_err());
+		assert!(calc_new_score(&game, 'A', 'W').is_err());
+	}
+
+	#[test]
+	fn test_lizard_spock() {
+		let game = rock_paper_scissors_lizard_spock();
+		let index = |name: &str| game.names.iter().position(|n| n == name).unwrap();
+		let wins = [
+			("Scissors", "Paper"), ("Paper", "Rock"), ("Rock", "Lizard"), ("Lizard", "Spock"), ("Spock", "Scissors"),
+			("Scissors", "Lizard"), ("Lizard", "Paper"), ("Paper", "Spock"), ("Spock", "Rock"), ("Rock", "Scissors"),
+		];
+
+		for (winner, loser) in wins {
+			assert_eq!(Outcome::Win, game.outcome(index(winner), index(loser)));
+			assert_eq!(Outcome::Loss, game.outcome(index(loser), index(winner)));
+		}
+
+		for opp in 0..game.move_count() {
+			for outcome in [Outcome::Loss, Outcome::Draw, Outcome::Win] {
+				assert_eq!(outcome, game.outcome(game.move_for_outcome(opp, outcome), opp));
+			}
+		}
+
+		// Spock vs scissors, and having to lose against rock with lizard or scissors
+		assert_eq!(Ok(2 + 6), calc_old_score(&game, 'E', 'W'));
+		assert_eq!(Ok(5), calc_new_score(&game, 'A', 'X'));
+	}
+
+	#[test]
+	fn test_decoder() {
+		let game = Game::rock_paper_scissors();
+		let report = decode_strategy_guide(&game, &parse_guide(TEST_INPUT)).unwrap();
+		let score_of = |kind: DecodingKind, meanings: &[&str]| {
+			report
+				.decodings
+				.iter()
+				.find(|d| d.kind == kind && d.assignments.iter().map(|(_, m)| m.as_str()).eq(meanings.iter().copied()))
+				.map(|d| d.score)
+		};
+
+		assert_eq!(12, report.decodings.len());
+		assert_eq!(Some(15), score_of(DecodingKind::Moves, &["Rock", "Paper", "Scissors"]));
+		assert_eq!(Some(12), score_of(DecodingKind::Outcomes, &["Loss", "Draw", "Win"]));
+		// Always winning, by reading Y, X and Z as paper, scissors and rock
+		assert_eq!(DecodingKind::Moves, report.best.kind);
+		assert_eq!(2 + 6 + 3 + 6 + 1 + 6, report.best.score);
+		assert!(report.worst.score <= 12);
+
+		let mean = report.decodings.iter().map(|d| d.score).sum::<i32>() as f64 / 12.0;
+		assert_eq!(mean, report.expected);
+	}
+
+	#[test]
+	fn test_invalid_games() {
+		assert!(Game::new(&[("Rock", 1), ("Paper", 2)], "AB", "XY", ['X', 'Y', 'Z']).is_err());
+		assert!(Game::new(&[("Rock", 1), ("Paper", 2), ("Scissors", 3)], "AAB", "XYZ", ['X', 'Y', 'Z']).is_err());
+		assert!(Game::new(&[("Rock", 1), ("Paper", 2), ("Scissors", 3)], "ABC", "XY", ['X', 'Y', 'Z']).is_err());
+		assert_eq!(
+			Err("Letter A is used more than once".to_string()),
+			Game::new(&[("Rock", 1), ("Paper", 2), ("Scissors", 3)], "ABA", "XYZ", ['X', 'Y', 'Z']).map(|_| ())
+		);
+		assert_eq!(
+			Err("Letter X is used more than once".to_string()),
+			Game::new(&[("Rock", 1), ("Paper", 2), ("Scissors", 3)], "ABC", "XYZ", ['X', 'Y', 'X']).map(|_| ())
+		);
+	}
+}
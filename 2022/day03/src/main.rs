@@ -1,122 +1,195 @@
+use std::fmt;
+
 use itertools::Itertools;
 
-fn main() {
-	let input = include_str!("input.txt");
-	let mut rucksack_sum: u32 = 0;
-	let mut group_sum: u32 = 0;
-	let mut lines = input.lines().peekable();
-
-	while lines.peek() != None {
-		let line1 = lines.next();
-		if let Some(li) = line1 {
-			rucksack_sum += sum_of_common_priorities_of_rucksack(li.to_string());
-		} else {
-			break;
-		}
+#[derive(Debug, PartialEq, Eq)]
+enum RucksackError {
+	InvalidItem(char),
+	UnevenCompartments { rucksack: String, compartments: usize },
+	IncompleteGroup { size: usize, expected: usize },
+	EmptyGroups,
+	NoCommonItem(Vec<String>),
+}
 
-		let line2 = lines.next();
-		if let Some(li) = line2 {
-			rucksack_sum += sum_of_common_priorities_of_rucksack(li.to_string());
-		} else {
-			break;
+impl fmt::Display for RucksackError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RucksackError::InvalidItem(c) => write!(f, "Invalid item type {c}"),
+			RucksackError::UnevenCompartments { rucksack, compartments } => {
+				write!(f, "Rucksack {rucksack} of length {} can't be split into {compartments} compartments", rucksack.len())
+			}
+			RucksackError::IncompleteGroup { size, expected } => write!(f, "Last group has {size} rucksacks instead of {expected}"),
+			RucksackError::EmptyGroups => write!(f, "Groups need at least one rucksack"),
+			RucksackError::NoCommonItem(items) => write!(f, "Did not find common items between:\n\t - {}", items.join("\n\t - ")),
 		}
+	}
+}
 
-		let line3 = lines.next();
-		if let Some(li) = line3 {
-			rucksack_sum += sum_of_common_priorities_of_rucksack(li.to_string());
-		} else {
-			break;
-		}
+/// Set of item types, bit i is set for the item type with priority i + 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct ItemSet(u64);
 
-		group_sum += find_group_score(line1.unwrap(), line2.unwrap(), line3.unwrap());
-	}
+impl ItemSet {
+	const ALL: ItemSet = ItemSet((1 << 52) - 1);
 
-	println!("\nrucksack_sum: {rucksack_sum}");
-	println!("group_sum: {group_sum}");
-}
+	fn from_items(items: &str) -> Result<Self, RucksackError> {
+		items
+			.chars()
+			.try_fold(ItemSet::default(), |set, c| Ok(set.union(ItemSet(1 << (type_to_score(c)? - 1)))))
+	}
 
-fn find_group_score(l1: &str, l2: &str, l3: &str) -> u32 {
-	let top = l1.chars().sorted().collect::<String>();
-	let mid = l2.chars().sorted().collect::<String>();
-	let bot = l3.chars().sorted().collect::<String>();
+	fn intersection(self, other: Self) -> Self {
+		ItemSet(self.0 & other.0)
+	}
 
-	let mid = mid.as_bytes();
-	let bot = bot.as_bytes();
-	let mut mid_index = 0;
-	let mut bot_index = 0;
+	fn union(self, other: Self) -> Self {
+		ItemSet(self.0 | other.0)
+	}
 
-	'outer: for c in top.chars() {
-		let mut c2 = mid[mid_index] as char;
-		let mut c3 = bot[bot_index] as char;
+	fn is_empty(self) -> bool {
+		self.0 == 0
+	}
 
-		while c > c2 {
-			mid_index += 1;
-			if mid_index >= mid.len() {
-				break 'outer;
-			}
+	fn priorities(self) -> impl Iterator<Item = u32> {
+		(0..52).filter(move |bit| self.0 & (1 << bit) != 0).map(|bit| bit + 1)
+	}
+}
 
-			c2 = mid[mid_index] as char;
-		}
+/// Item types found in every one of the given item lists.
+fn find_common_items(item_lists: &[&str]) -> Result<ItemSet, RucksackError> {
+	let common = item_lists
+		.iter()
+		.map(|items| ItemSet::from_items(items))
+		.fold_ok(ItemSet::ALL, ItemSet::intersection)?;
 
-		while c2 > c3 {
-			bot_index += 1;
-			if bot_index >= bot.len() {
-				break 'outer;
-			}
+	if common.is_empty() {
+		return Err(RucksackError::NoCommonItem(item_lists.iter().map(|items| items.chars().sorted().collect()).collect()));
+	}
 
-			c3 = bot[bot_index] as char;
-		}
+	Ok(common)
+}
 
-		if c == c2 && c2 == c3 {
-			return type_to_score(c);
-		}
+fn sum_of_common_priorities_of_rucksack(rucksack: &str, compartments: usize) -> Result<u32, RucksackError> {
+	// Checked up front so splitting the rucksack by byte length is safe
+	if let Some(c) = rucksack.chars().find(|c| !c.is_ascii_alphabetic()) {
+		return Err(RucksackError::InvalidItem(c));
 	}
+	if compartments == 0 || !rucksack.len().is_multiple_of(compartments) {
+		return Err(RucksackError::UnevenCompartments { rucksack: rucksack.to_string(), compartments });
+	}
+
+	let compartment_len = rucksack.len() / compartments;
+	let compartments: Vec<&str> = (0..compartments)
+		.map(|i| &rucksack[i * compartment_len..(i + 1) * compartment_len])
+		.collect();
 
-	let top = l1.chars().sorted().collect::<String>();
-	let mid = l2.chars().sorted().collect::<String>();
-	let bot = l3.chars().sorted().collect::<String>();
-	println!("Intersection not found");
-	println!("\t - {top}");
-	println!("\t - {mid}");
-	println!("\t - {bot}");
-	panic!("Did not find common elements between 3 elves");
+	Ok(find_common_items(&compartments)?.priorities().sum())
 }
 
-fn sum_of_common_priorities_of_rucksack(rucksack: String) -> u32 {
-	let (comp1, comp2) = rucksack.split_at(rucksack.len() / 2);
-	type_to_score(find_common_elements(&comp1, &comp2))
+fn find_group_score(group: &[&str]) -> Result<u32, RucksackError> {
+	Ok(find_common_items(group)?.priorities().sum())
 }
 
-fn find_common_elements(left_side: &str, right_side: &str) -> char { 
-	let left_side = left_side.chars().sorted().collect::<String>();
-	let right_side = right_side.chars().sorted().collect::<String>();
+fn calc_sums(input: &str, compartments: usize, group_size: usize) -> Result<(u32, u32), RucksackError> {
+	let lines: Vec<&str> = input.lines().filter(|line| !line.is_empty()).collect();
+	let mut rucksack_sum = 0;
+	let mut group_sum = 0;
 
-	let bytes = right_side.as_bytes();
-	let mut index = 0;
-	'outer: for c in left_side.chars() {
-		let mut c2 = bytes[index] as char;
+	for line in &lines {
+		rucksack_sum += sum_of_common_priorities_of_rucksack(line, compartments)?;
+	}
 
-		while c > c2 {
-			index += 1;
-			if index >= bytes.len() {
-				break 'outer;
-			}
+	if group_size == 0 {
+		return Err(RucksackError::EmptyGroups);
+	}
+	if !lines.len().is_multiple_of(group_size) {
+		return Err(RucksackError::IncompleteGroup { size: lines.len() % group_size, expected: group_size });
+	}
 
-			c2 = bytes[index] as char;
-		}
+	for group in lines.chunks(group_size) {
+		group_sum += find_group_score(group)?;
+	}
+
+	Ok((rucksack_sum, group_sum))
+}
+
+fn main() {
+	let input = include_str!("input.txt");
 
-		if c == c2 {
-			return c;
+	match calc_sums(input, 2, 3) {
+		Ok((rucksack_sum, group_sum)) => {
+			println!("\nrucksack_sum: {rucksack_sum}");
+			println!("group_sum: {group_sum}");
+		}
+		Err(err) => {
+			eprintln!("Error: {err}");
+			std::process::exit(1);
 		}
 	}
-
-	panic!("Did not find common elements between 2 compartments of a ruckasck");
 }
 
+fn type_to_score(t: char) -> Result<u32, RucksackError> {
+	if !t.is_ascii_alphabetic() {
+		return Err(RucksackError::InvalidItem(t));
+	}
 
-fn type_to_score(t: char) -> u32 {
 	let letter_value = t.to_ascii_lowercase() as u32 - 'a' as u32 + 1;
 	let case_value = if t.is_lowercase() { 0 } else { 26 };
-	
-	letter_value + case_value
+
+	Ok(letter_value + case_value)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	static TEST_INPUT: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw";
+
+	#[test]
+	fn test_first_half() {
+		assert_eq!(157, calc_sums(TEST_INPUT, 2, 3).unwrap().0);
+	}
+
+	#[test]
+	fn test_second_half() {
+		assert_eq!(70, calc_sums(TEST_INPUT, 2, 3).unwrap().1);
+	}
+
+	#[test]
+	fn test_item_set() {
+		let set = ItemSet::from_items("aAzZa").unwrap();
+
+		assert_eq!(vec![1, 26, 27, 52], set.priorities().collect::<Vec<u32>>());
+		assert_eq!(ItemSet::from_items("a").unwrap(), set.intersection(ItemSet::from_items("ab").unwrap()));
+		assert_eq!(ItemSet::ALL, ItemSet::from_items("abcdefghijklmnopqrstuvwxyz").unwrap().union(ItemSet::from_items("ABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap()));
+		assert_eq!(Err(RucksackError::InvalidItem('1')), ItemSet::from_items("a1"));
+	}
+
+	#[test]
+	fn test_group_sizes() {
+		// Pairs of the example have s, F, r, f, M then B, T, v, w, q then Z, J, G in common
+		assert_eq!(Ok((157, 371)), calc_sums(TEST_INPUT, 2, 2));
+		assert!(matches!(calc_sums(TEST_INPUT, 2, 6), Err(RucksackError::NoCommonItem(_))));
+
+		// Every rucksack has a in both compartments, and only a is shared by all of them
+		let shared_a = "aBaC\naDaE\naFaG\naHaI\naJaK\naLaM";
+		assert_eq!(Ok((6, 1)), calc_sums(shared_a, 2, 6));
+		assert_eq!(Ok((6, 3)), calc_sums(shared_a, 2, 2));
+
+		assert_eq!(Err(RucksackError::IncompleteGroup { size: 2, expected: 4 }), calc_sums(TEST_INPUT, 2, 4));
+		assert_eq!(Err(RucksackError::IncompleteGroup { size: 1, expected: 5 }), calc_sums(shared_a, 2, 5));
+		assert_eq!(Err(RucksackError::EmptyGroups), calc_sums(TEST_INPUT, 2, 0));
+	}
+
+	#[test]
+	fn test_compartments() {
+		assert_eq!(Ok(1), sum_of_common_priorities_of_rucksack("abcaxyaqr", 3));
+		assert!(matches!(sum_of_common_priorities_of_rucksack("abc", 2), Err(RucksackError::UnevenCompartments { .. })));
+		assert!(matches!(sum_of_common_priorities_of_rucksack("abcd", 2), Err(RucksackError::NoCommonItem(_))));
+	}
 }
@@ -1,58 +1,234 @@
-use std::num::ParseIntError;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use itertools::Itertools;
+
+/// Allen's interval relations, of a first assignment relative to a second one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Relation {
+    Before,
+    Meets,
+    Overlaps,
+    Starts,
+    During,
+    Finishes,
+    Equals,
+    FinishedBy,
+    Contains,
+    StartedBy,
+    OverlappedBy,
+    MetBy,
+    After,
+}
+
+impl Relation {
+    fn inverse(self) -> Relation {
+        match self {
+            Relation::Before => Relation::After,
+            Relation::Meets => Relation::MetBy,
+            Relation::Overlaps => Relation::OverlappedBy,
+            Relation::Starts => Relation::StartedBy,
+            Relation::During => Relation::Contains,
+            Relation::Finishes => Relation::FinishedBy,
+            Relation::Equals => Relation::Equals,
+            Relation::FinishedBy => Relation::Finishes,
+            Relation::Contains => Relation::During,
+            Relation::StartedBy => Relation::Starts,
+            Relation::OverlappedBy => Relation::Overlaps,
+            Relation::MetBy => Relation::Meets,
+            Relation::After => Relation::Before,
+        }
+    }
+
+    /// One of the assignments covers every section of the other one.
+    fn is_containment(self) -> bool {
+        matches!(
+            self,
+            Relation::Starts
+                | Relation::During
+                | Relation::Finishes
+                | Relation::Equals
+                | Relation::FinishedBy
+                | Relation::Contains
+                | Relation::StartedBy
+        )
+    }
+
+    /// The assignments share at least one section.
+    fn shares_sections(self) -> bool {
+        !matches!(self, Relation::Before | Relation::Meets | Relation::MetBy | Relation::After)
+    }
+}
+
+trait InclusiveRangeExt {
+    fn relation(&self, other: &Self) -> Relation;
+}
+
+impl InclusiveRangeExt for RangeInclusive<u32> {
+    fn relation(&self, other: &Self) -> Relation {
+        // Sections are whole units, so an assignment covers [start, end + 1) and
+        // two assignments meet when they are adjacent without sharing a section
+        let (s1, e1) = (u64::from(*self.start()), u64::from(*self.end()) + 1);
+        let (s2, e2) = (u64::from(*other.start()), u64::from(*other.end()) + 1);
+
+        // Only the relations where self starts first (or ends first from the same start)
+        // are classified directly, the others are their inverses
+        if s1 > s2 || (s1 == s2 && e1 > e2) {
+            return other.relation(self).inverse();
+        }
+
+        if s1 == s2 {
+            if e1 == e2 { Relation::Equals } else { Relation::Starts }
+        } else if e1 < s2 {
+            Relation::Before
+        } else if e1 == s2 {
+            Relation::Meets
+        } else if e1 < e2 {
+            Relation::Overlaps
+        } else if e1 == e2 {
+            Relation::FinishedBy
+        } else {
+            Relation::Contains
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Report {
+    contain_count: usize,
+    overlap_count: usize,
+    relation_counts: BTreeMap<Relation, usize>,
+}
+
+fn str_to_sections_bounds(section: &str) -> Result<RangeInclusive<u32>, String> {
+    let (start, end) = section
+        .split('-')
+        .map(|x| x.trim().parse::<u32>().map_err(|err| format!("Invalid section bound in {section}: {err}")))
+        .collect_tuple()
+        .ok_or_else(|| format!("Expected start-end sections, got {section}"))?;
+    let (start, end) = (start?, end?);
+
+    if start > end {
+        return Err(format!("Assignment {section} ends before it starts"));
+    }
+
+    Ok(start..=end)
+}
+
+fn parse_line(line: &str) -> Result<Vec<RangeInclusive<u32>>, String> {
+    line.split(',').map(str_to_sections_bounds).collect()
+}
+
+/// Relations between every pair of assignments of a line, in the order they appear.
+fn classify_line(assignments: &[RangeInclusive<u32>]) -> Vec<Relation> {
+    assignments
+        .iter()
+        .tuple_combinations()
+        .map(|(first, second)| first.relation(second))
+        .collect()
+}
+
+/// A line counts as containing or overlapping if any of its pairs does.
+fn analyze(input: &str) -> Result<Report, String> {
+    let mut report = Report::default();
+
+    for (index, line) in input.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+        let assignments = parse_line(line).map_err(|err| format!("Line {}: {err}", index + 1))?;
+        let relations = classify_line(&assignments);
+
+        for relation in &relations {
+            *report.relation_counts.entry(*relation).or_default() += 1;
+        }
+        if relations.iter().any(|relation| relation.is_containment()) {
+            report.contain_count += 1;
+        }
+        if relations.iter().any(|relation| relation.shares_sections()) {
+            report.overlap_count += 1;
+        }
+    }
+
+    Ok(report)
+}
 
 fn main() {
     let input_txt = include_str!("input.txt");
-    let pairs_of_bounds = input_txt
-        .lines()
-        .filter_map(|line| {
-            let pairs_of_section_bounds: Vec<_> = line.split(',')
-                .take(2)
-                .filter_map(|section| str_to_sections_bounds(section).ok())
-                .collect();
-
-            if pairs_of_section_bounds.len() == 2 {
-                Some(pairs_of_section_bounds)
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<Vec<(u32, u32)>>>();
-
-    let contain_count = pairs_of_bounds.iter()
-        .filter(|pair_of_bounds| {
-            let (start1, end1) = pair_of_bounds[0];
-            let (start2, end2) = pair_of_bounds[1];
-
-            let first_is_inside_second = start1 >= start2 && end1 <= end2;
-            let second_is_inside_first = start1 <= start2 && end1 >= end2;
-            
-            first_is_inside_second || second_is_inside_first
-        })
-        .count();
-
-    let overlap_count = pairs_of_bounds.iter()
-        .filter(|pair_of_bounds| {
-            let (start1, end1) = pair_of_bounds[0];
-            let (start2, end2) = pair_of_bounds[1];
-            let range1 = start1..=end1;
-            let range2 = start2..=end2;
-
-            range1.contains(&start2) || range1.contains(&end2) || range2.contains(&start1) || range2.contains(&end1)
-        })
-        .count();
-
-    println!("{}", contain_count);
-    println!("{}", overlap_count);
-}
-
-fn str_to_sections_bounds(section: &str) -> Result<(u32, u32), ParseIntError> {
-    let mut bounds = section
-        .split('-')
-        .take(2)
-        .map(|x| x.parse::<u32>());
-    
-    let start = bounds.next().unwrap_or(Ok(0))?;
-    let end = bounds.next().unwrap_or(Ok(0))?;
+    let report = analyze(input_txt).unwrap_or_else(|err| panic!("{err}"));
+
+    println!("{}", report.contain_count);
+    println!("{}", report.overlap_count);
+    println!();
+    for (relation, count) in &report.relation_counts {
+        println!("{relation:?}: {count}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT: &str = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+
+    #[test]
+    fn test_first_half() {
+        assert_eq!(2, analyze(TEST_INPUT).unwrap().contain_count);
+    }
+
+    #[test]
+    fn test_second_half() {
+        assert_eq!(4, analyze(TEST_INPUT).unwrap().overlap_count);
+    }
+
+    #[test]
+    fn test_relations() {
+        let cases = [
+            (1..=2, 4..=5, Relation::Before),
+            (1..=3, 4..=5, Relation::Meets),
+            (1..=4, 4..=5, Relation::Overlaps),
+            (4..=4, 4..=5, Relation::Starts),
+            (3..=4, 2..=5, Relation::During),
+            (5..=5, 4..=5, Relation::Finishes),
+            (4..=5, 4..=5, Relation::Equals),
+        ];
+
+        for (first, second, relation) in cases {
+            assert_eq!(relation, first.relation(&second), "{first:?} {second:?}");
+            assert_eq!(relation.inverse(), second.relation(&first), "{second:?} {first:?}");
+        }
+    }
+
+    #[test]
+    fn test_relation_counts() {
+        let report = analyze(TEST_INPUT).unwrap();
+        let expected = BTreeMap::from([
+            (Relation::Before, 1),
+            (Relation::Meets, 1),
+            (Relation::Overlaps, 2),
+            (Relation::Contains, 1),
+            (Relation::Finishes, 1),
+        ]);
+
+        assert_eq!(expected, report.relation_counts);
+    }
+
+    #[test]
+    fn test_more_than_two_elves() {
+        let report = analyze("1-2,4-6,5-6\n1-3,7-9,4-6").unwrap();
+
+        assert_eq!(1, report.contain_count);
+        assert_eq!(1, report.overlap_count);
+        assert_eq!(6, report.relation_counts.values().sum::<usize>());
+        assert_eq!(vec![Relation::Before, Relation::Meets, Relation::MetBy], classify_line(&parse_line("1-3,7-9,4-6").unwrap()));
+    }
 
-    Ok((start, end))
+    #[test]
+    fn test_parse_errors() {
+        assert!(analyze("2-4,6").is_err());
+        assert!(analyze("4-2,6-8").is_err());
+        assert!(analyze("2-4,a-8").is_err());
+    }
 }
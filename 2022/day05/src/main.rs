@@ -1,3 +1,4 @@
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 
 use itertools::Itertools;
@@ -23,8 +24,8 @@ impl FromStr for Move {
             .collect_tuple()
             .ok_or(MoveParseError)?;
         // reduce by one since stacks start at 1, and indices at 0
-        let origin = origin - 1;
-        let destination = destination - 1;
+        let origin = usize::checked_sub(origin, 1).ok_or(MoveParseError)?;
+        let destination = usize::checked_sub(destination, 1).ok_or(MoveParseError)?;
 
         Ok(Move {
             count,
@@ -58,58 +59,279 @@ fn parse_stacks(stack_str: String) -> Vec<Vec<char>> {
     for line in stacks_and_indices {
         line.chars()
             .enumerate()
-            .filter(|(index, c)| index % 4 == 1 && c.is_alphabetic())
+            .filter(|(index, c)| index % 4 == 1 && !c.is_whitespace())
             .for_each(|(index, el)| stacks[(index - 1) / 4].push(el));
     }
 
     stacks
 }
 
-fn apply_moves_to_stacks(
-    stacks: &Vec<Vec<char>>,
-    moves: &Vec<Move>,
-    reverse_appending: bool,
-) -> Vec<Vec<char>> {
-    let mut stacks = stacks.to_vec();
+#[derive(Debug, PartialEq, Eq)]
+enum MoveError {
+    // Indices of moves and stacks are 0 based
+    NoSuchStack { move_index: usize, stack: usize },
+    Underflow { move_index: usize, stack: usize, requested: usize, available: usize },
+}
 
-    for mv in moves {
-        let top_of_the_stack_range = (stacks[mv.origin].len() - mv.count)..stacks[mv.origin].len();
-        let to_move = stacks[mv.origin]
-            .drain(top_of_the_stack_range)
-            .collect::<Vec<char>>();
-        if reverse_appending {
-            stacks[mv.destination].extend(to_move.iter().rev());
-        } else {
-            stacks[mv.destination].extend(to_move.iter());
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::NoSuchStack { move_index, stack } => {
+                write!(f, "Move {} refers to stack {} which does not exist", move_index + 1, stack + 1)
+            }
+            MoveError::Underflow { move_index, stack, requested, available } => write!(
+                f,
+                "Move {} takes {requested} crates from stack {} which only has {available}",
+                move_index + 1,
+                stack + 1
+            ),
         }
     }
+}
 
-    stacks
+trait Crane {
+    /// Moves the top `count` crates of `from` onto `to`, `from` is guaranteed to hold enough crates.
+    fn move_crates(&self, from: &mut Vec<char>, to: &mut Vec<char>, count: usize);
 }
 
-fn main() {
-    let input_txt = include_str!("input.txt");
+/// Moves crates one at a time, reversing their order.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn move_crates(&self, from: &mut Vec<char>, to: &mut Vec<char>, count: usize) {
+        to.extend(from.drain(from.len() - count..).rev());
+    }
+}
+
+/// Moves all the crates at once, keeping their order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn move_crates(&self, from: &mut Vec<char>, to: &mut Vec<char>, count: usize) {
+        to.extend(from.drain(from.len() - count..));
+    }
+}
+
+/// Lifts at most `capacity` crates at a time, each lift keeping the order of its crates.
+struct CappedCrane {
+    capacity: NonZeroUsize,
+}
+
+impl Crane for CappedCrane {
+    fn move_crates(&self, from: &mut Vec<char>, to: &mut Vec<char>, count: usize) {
+        let mut left = count;
+        while left > 0 {
+            let lift = left.min(self.capacity.get());
+            to.extend(from.drain(from.len() - lift..));
+            left -= lift;
+        }
+    }
+}
+
+fn apply_move(crane: &impl Crane, stacks: &mut [Vec<char>], mv: &Move, move_index: usize) -> Result<(), MoveError> {
+    for stack in [mv.origin, mv.destination] {
+        if stack >= stacks.len() {
+            return Err(MoveError::NoSuchStack { move_index, stack });
+        }
+    }
+
+    let available = stacks[mv.origin].len();
+    if available < mv.count {
+        return Err(MoveError::Underflow { move_index, stack: mv.origin, requested: mv.count, available });
+    }
+
+    if mv.origin == mv.destination {
+        // Every crane puts the crates back where they were
+        return Ok(());
+    }
+
+    // Take the destination out so both stacks can be borrowed mutably
+    let mut destination = std::mem::take(&mut stacks[mv.destination]);
+    crane.move_crates(&mut stacks[mv.origin], &mut destination, mv.count);
+    stacks[mv.destination] = destination;
+
+    Ok(())
+}
+
+/// Executes the moves one by one, yielding the stacks after each of them.
+/// Stops after the first invalid move.
+struct Steps<'a, C: Crane> {
+    crane: &'a C,
+    stacks: Vec<Vec<char>>,
+    moves: std::iter::Enumerate<std::slice::Iter<'a, Move>>,
+    failed: bool,
+}
+
+impl<'a, C: Crane> Steps<'a, C> {
+    fn new(crane: &'a C, stacks: &[Vec<char>], moves: &'a [Move]) -> Self {
+        Steps { crane, stacks: stacks.to_vec(), moves: moves.iter().enumerate(), failed: false }
+    }
+}
+
+impl<C: Crane> Iterator for Steps<'_, C> {
+    type Item = Result<Vec<Vec<char>>, MoveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let (move_index, mv) = self.moves.next()?;
+        match apply_move(self.crane, &mut self.stacks, mv, move_index) {
+            Ok(()) => Some(Ok(self.stacks.clone())),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn apply_moves_to_stacks(crane: &impl Crane, stacks: &[Vec<char>], moves: &[Move]) -> Result<Vec<Vec<char>>, MoveError> {
+    let mut stacks = stacks.to_vec();
+
+    for (move_index, mv) in moves.iter().enumerate() {
+        apply_move(crane, &mut stacks, mv, move_index)?;
+    }
+
+    Ok(stacks)
+}
+
+/// Draws the stacks in the same format as the puzzle input, so the result can be read back with `parse_stacks`.
+fn render_stacks(stacks: &[Vec<char>]) -> String {
+    let height = stacks.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut lines: Vec<String> = (0..height)
+        .rev()
+        .map(|level| {
+            stacks
+                .iter()
+                .map(|stack| stack.get(level).map_or("   ".to_string(), |c| format!("[{c}]")))
+                .join(" ")
+        })
+        .collect();
+    // Numbers with more than one digit fill the column instead of being centered
+    lines.push((1..=stacks.len()).map(|number| format!(" {number:<2}")).join(" "));
+
+    lines.join("\n")
+}
+
+fn top_crates(stacks: &[Vec<char>]) -> String {
+    stacks.iter().map(|stack| stack.last().unwrap_or(&' ')).join("")
+}
+
+fn parse_input(input_txt: &str) -> (Vec<Vec<char>>, Vec<Move>) {
     // Split input text into initial state (stacks) and moves to be executed
     let (stacks, moves) = input_txt.split("\n\n").take(2).collect_tuple().unwrap();
 
-    // Parse moves and stacks
-    let moves = parse_moves(moves.to_string());
-    let stacks = parse_stacks(stacks.to_string());
+    (parse_stacks(stacks.to_string()), parse_moves(moves.to_string()))
+}
+
+fn main() {
+    let input_txt = include_str!("input.txt");
+    let (stacks, moves) = parse_input(input_txt);
 
     // stacks after moves as per first and second half rules
-    let first_answer = apply_moves_to_stacks(&stacks, &moves, true);
-    let second_answer = apply_moves_to_stacks(&stacks, &moves, false);
-
-    // stringify
-    let first_output = first_answer
-        .iter()
-        .map(|vec| vec.into_iter().rev().next().unwrap_or(&' '))
-        .join("");
-    let second_output = second_answer
-        .iter()
-        .map(|vec| vec.into_iter().rev().next().unwrap_or(&' '))
-        .join("");
-
-    println!("{first_output}");
-    println!("{second_output}");
+    let first_answer = apply_moves_to_stacks(&CrateMover9000, &stacks, &moves).unwrap_or_else(|err| panic!("{err}"));
+    let second_answer = apply_moves_to_stacks(&CrateMover9001, &stacks, &moves).unwrap_or_else(|err| panic!("{err}"));
+
+    println!("{}", top_crates(&first_answer));
+    println!("{}", top_crates(&second_answer));
+
+    if let Some(capacity) = std::env::args().nth(1).and_then(|arg| arg.parse().ok()) {
+        let crane = CappedCrane { capacity };
+        for (index, step) in Steps::new(&crane, &stacks, &moves).enumerate() {
+            match step {
+                Ok(stacks) => println!("After move {}:\n{}\n", index + 1, render_stacks(&stacks)),
+                Err(err) => println!("{err}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_INPUT: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    #[test]
+    fn test_first_half() {
+        let (stacks, moves) = parse_input(TEST_INPUT);
+        assert_eq!("CMZ", top_crates(&apply_moves_to_stacks(&CrateMover9000, &stacks, &moves).unwrap()));
+    }
+
+    #[test]
+    fn test_second_half() {
+        let (stacks, moves) = parse_input(TEST_INPUT);
+        assert_eq!("MCD", top_crates(&apply_moves_to_stacks(&CrateMover9001, &stacks, &moves).unwrap()));
+    }
+
+    #[test]
+    fn test_capped_crane() {
+        let (stacks, moves) = parse_input(TEST_INPUT);
+        let single = CappedCrane { capacity: NonZeroUsize::new(1).unwrap() };
+        let unbounded = CappedCrane { capacity: NonZeroUsize::new(usize::MAX).unwrap() };
+        let double = CappedCrane { capacity: NonZeroUsize::new(2).unwrap() };
+
+        assert_eq!("CMZ", top_crates(&apply_moves_to_stacks(&single, &stacks, &moves).unwrap()));
+        assert_eq!("MCD", top_crates(&apply_moves_to_stacks(&unbounded, &stacks, &moves).unwrap()));
+        assert_eq!("MCZ", top_crates(&apply_moves_to_stacks(&double, &stacks, &moves).unwrap()));
+    }
+
+    #[test]
+    fn test_steps() {
+        let (stacks, moves) = parse_input(TEST_INPUT);
+        let steps: Vec<Vec<Vec<char>>> = Steps::new(&CrateMover9000, &stacks, &moves).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(4, steps.len());
+        assert_eq!(vec![vec!['Z', 'N', 'D'], vec!['M', 'C'], vec!['P']], steps[0]);
+        assert_eq!(apply_moves_to_stacks(&CrateMover9000, &stacks, &moves).unwrap(), steps[3]);
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let (diagram, _) = TEST_INPUT.split_once("\n\n").unwrap();
+        assert_eq!(diagram, render_stacks(&parse_stacks(diagram.to_string())));
+
+        let stacks: Vec<Vec<char>> = (0..12)
+            .map(|i| ('A'..='Z').skip(i).step_by(i + 1).take(i % 5).collect())
+            .collect();
+        assert_eq!(stacks, parse_stacks(render_stacks(&stacks)));
+
+        // Crates can be marked with any character but a space
+        let stacks = vec![vec!['1', '%'], vec![], vec!['a', 'Z', '[']];
+        assert_eq!(stacks, parse_stacks(render_stacks(&stacks)));
+
+        let (stacks, moves) = parse_input(TEST_INPUT);
+        for step in Steps::new(&CrateMover9001, &stacks, &moves) {
+            let step = step.unwrap();
+            assert_eq!(step, parse_stacks(render_stacks(&step)));
+        }
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let (stacks, _) = parse_input(TEST_INPUT);
+        let moves = parse_moves("move 1 from 1 to 2\nmove 4 from 1 to 3\nmove 1 from 1 to 2".to_string());
+
+        let steps: Vec<_> = Steps::new(&CrateMover9001, &stacks, &moves).collect();
+        assert_eq!(2, steps.len());
+        assert_eq!(Err(MoveError::Underflow { move_index: 1, stack: 0, requested: 4, available: 1 }), steps[1]);
+
+        let moves = parse_moves("move 1 from 1 to 4".to_string());
+        assert_eq!(
+            Err(MoveError::NoSuchStack { move_index: 0, stack: 3 }),
+            apply_moves_to_stacks(&CrateMover9001, &stacks, &moves)
+        );
+        assert_eq!(Err(MoveParseError), Move::from_str("move 1 from 0 to 4"));
+    }
 }
@@ -1,36 +1,73 @@
-#[derive(Debug, PartialEq)]
-enum DuplicateIndex {
-    Index(usize),
-    NoDuplicates,
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+
+/// Detects windows of `len` distinct bytes in a single pass, by remembering where each byte was last seen.
+struct MarkerDetector {
+    len: usize,
+    // 1 based position of the last occurrence of each byte, 0 if never seen
+    last_seen: [usize; 256],
+    // 1 based position of the first byte of the longest run of distinct bytes ending at the last byte read
+    run_start: usize,
+    position: usize,
 }
 
-fn find_first_duplicate(s: &[u8]) -> DuplicateIndex {
-    for i in 0..s.len() - 1 {
-        for j in i + 1..s.len() {
-            if s[i] == s[j] {
-                return DuplicateIndex::Index(i);
-            }
+impl MarkerDetector {
+    fn new(len: usize) -> Self {
+        MarkerDetector { len, last_seen: [0; 256], run_start: 1, position: 0 }
+    }
+
+    /// Reads the next byte, returning the number of bytes read so far if they end with a marker.
+    fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+
+        let last_seen = &mut self.last_seen[byte as usize];
+        if *last_seen >= self.run_start {
+            self.run_start = *last_seen + 1;
         }
+        *last_seen = self.position;
+
+        (self.position + 1 - self.run_start >= self.len).then_some(self.position)
     }
+}
+
+/// End positions of every marker of the stream, buffering its reads.
+fn find_markers(reader: impl Read, len: usize) -> impl Iterator<Item = io::Result<usize>> {
+    let mut detector = MarkerDetector::new(len);
 
-    DuplicateIndex::NoDuplicates
+    BufReader::new(reader).bytes().filter_map(move |byte| match byte {
+        Ok(byte) => detector.push(byte).map(Ok),
+        Err(err) => Some(Err(err)),
+    })
 }
 
 fn find_end_of_first_unique_substr(data: &[u8], len: usize) -> Result<usize, &'static str> {
-    let mut i = 0;
-    while i + len - 1 < data.len() {
-        i = i + match find_first_duplicate(&data[i..i + len]) {
-            DuplicateIndex::NoDuplicates => {
-                return Ok(i + len);
-            }
-            DuplicateIndex::Index(ind) => ind + 1,
-        }
+    if len == 0 {
+        return Err("Window length should be a positive integer");
     }
 
-    Err("Reached end of data with no window of non duplicates found")
+    match find_markers(data, len).next() {
+        Some(Ok(i)) => Ok(i),
+        Some(Err(_)) => Err("Failed to read data"),
+        None => Err("Reached end of data with no window of non duplicates found"),
+    }
 }
 
 fn main() {
+    // With a file and a window length, streams the file and reports every marker in it
+    let mut args = std::env::args().skip(1);
+    if let (Some(path), Some(len)) = (args.next(), args.next()) {
+        let len = len.parse().ok().filter(|&len| len > 0).expect("Window length should be a positive integer");
+        let file = File::open(&path).unwrap_or_else(|err| panic!("Could not open {path}: {err}"));
+
+        for marker in find_markers(file, len) {
+            match marker {
+                Ok(i) => println!("{}", i),
+                Err(err) => println!("Error: {}", err),
+            }
+        }
+        return;
+    }
+
     let data = include_str!("input.txt").as_bytes();
 
     match find_end_of_first_unique_substr(data, 4) {
@@ -78,28 +115,68 @@ mod tests {
         assert_eq!(find_end_of_first_unique_substr(input, 14), Ok(26));
     }
 
-    // Not really needed, written for practice
+    fn all_markers(data: &[u8], len: usize) -> Vec<usize> {
+        find_markers(data, len).collect::<io::Result<_>>().unwrap()
+    }
+
     #[test]
-    fn test_check_eq_with_duplicates() {
+    fn test_first_unique_substr_with_duplicates() {
         let input = "mjqj".as_bytes();
-        assert_eq!(find_first_duplicate(input), DuplicateIndex::Index(1));
+        assert_eq!(find_end_of_first_unique_substr(input, 4), Err("Reached end of data with no window of non duplicates found"));
     }
 
     #[test]
-    fn test_check_eq_with_no_duplicates() {
+    fn test_first_unique_substr_with_no_duplicates() {
         let input = "mjqw".as_bytes();
-        assert_eq!(find_first_duplicate(input), DuplicateIndex::NoDuplicates);
+        assert_eq!(find_end_of_first_unique_substr(input, 4), Ok(4));
     }
 
     #[test]
-    fn test_check_eq_with_small_input() {
+    fn test_first_unique_substr_with_small_input() {
         let input = "m".as_bytes();
-        assert_eq!(find_first_duplicate(input), DuplicateIndex::NoDuplicates);
+        assert_eq!(find_end_of_first_unique_substr(input, 1), Ok(1));
+        assert!(find_end_of_first_unique_substr(input, 2).is_err());
     }
 
     #[test]
-    fn test_check_eq_with_large_input() {
+    fn test_first_unique_substr_with_empty_window() {
+        let input = "mjqw".as_bytes();
+        assert_eq!(find_end_of_first_unique_substr(input, 0), Err("Window length should be a positive integer"));
+    }
+
+    #[test]
+    fn test_first_unique_substr_with_large_input() {
         let input = "mjqwtxyzp".as_bytes();
-        assert_eq!(find_first_duplicate(input), DuplicateIndex::NoDuplicates);
+        assert_eq!(find_end_of_first_unique_substr(input, 9), Ok(9));
+    }
+
+    #[test]
+    fn test_all_markers() {
+        assert_eq!(vec![3, 4, 5, 6], all_markers(b"abcabc", 3));
+        assert_eq!(vec![3, 4, 8], all_markers(b"abcdd\xffd\x00", 3));
+        assert_eq!(vec![1, 2, 3], all_markers(b"aaa", 1));
+        assert!(all_markers(b"aaa", 2).is_empty());
+    }
+
+    #[test]
+    fn test_against_naive_windows() {
+        // Small alphabet so that duplicates are common
+        let mut seed: u32 = 7;
+        let data: Vec<u8> = (0..2000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                b"abcdefgh"[(seed >> 16) as usize % 8]
+            })
+            .collect();
+
+        for len in 1..=8 {
+            let naive: Vec<usize> = data
+                .windows(len)
+                .enumerate()
+                .filter(|(_, window)| window.iter().enumerate().all(|(i, c)| !window[i + 1..].contains(c)))
+                .map(|(i, _)| i + len)
+                .collect();
+            assert_eq!(naive, all_markers(&data, len), "window of {len}");
+        }
     }
 }
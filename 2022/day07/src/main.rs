@@ -1,35 +1,67 @@
 use itertools::Itertools;
 
+#[derive(Eq, PartialEq, Debug, Clone)]
+struct File {
+    name: String,
+    size: u128,
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 struct Folder {
     name: String,
+    files: Vec<File>,
     files_size: u128, // size of files in dir
     total_size: u128, // total size including subrdirs, sub sub folders, ...
     children: Vec<usize>,
     parent: usize
 }
 
-fn print_folder_tree(folders: &[Folder], index: usize, depth: u32) {
+impl Folder {
+    fn new(name: &str, parent: usize) -> Self {
+        Folder {
+            name: name.to_string(),
+            files: vec![],
+            files_size: 0,
+            total_size: 0,
+            children: vec![],
+            parent,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+enum Entry<'a> {
+    Folder(usize),
+    File(&'a File),
+}
+
+#[cfg(test)]
+fn render_folder_tree(folders: &[Folder], index: usize, depth: usize) -> String {
     let folder = &folders[index];
-    for _ in 0..depth {
-        print!("----");
-    } print!("  ");
-    
-    println!("{} {} {}", folder.name, folder.files_size, folder.total_size);
+    let mut tree = format!("{}  {} {} {}\n", "----".repeat(depth), folder.name, folder.files_size, folder.total_size);
 
     for &child_index in &folder.children {
-        print_folder_tree(folders, child_index, depth + 1);
+        tree += &render_folder_tree(folders, child_index, depth + 1);
     }
+
+    tree
+}
+
+/// Index of the child folder with the given name, created if the log did not mention it before.
+fn child_folder(folders: &mut Vec<Folder>, parent: usize, name: &str) -> usize {
+    if let Some(&index) = folders[parent].children.iter().find(|&&index| folders[index].name == name) {
+        return index;
+    }
+
+    folders.push(Folder::new(name, parent));
+    let new_index = folders.len() - 1;
+    folders[parent].children.push(new_index);
+
+    new_index
 }
 
 fn parse_terminal_output(output: &str) -> Option<Vec<Folder>> {
-    let mut folders = vec![Folder {
-        name: "/".to_string(),
-        files_size: 0,
-        total_size: 0,
-        children: vec![],
-        parent: 0,
-    }];
+    let mut folders = vec![Folder::new("/", 0)];
     let mut curr_index = 0; // root index
 
     for line in output.lines() {
@@ -40,38 +72,99 @@ fn parse_terminal_output(output: &str) -> Option<Vec<Folder>> {
         match line.split(" ").collect_vec()[..] {
             ["$", "ls"] => { /* Ignore */ },
             ["$", "cd", "/"] => { curr_index = 0; },
-            ["dir", _] => { /* Ignore, since we already have the info via cd commmands */},
             ["$", "cd", ".."] => {
                 let curr_folder = &folders[curr_index];
                 curr_index = curr_folder.parent;
             },
             ["$", "cd", sub_path] => {
-                let new_folder = Folder {
-                    name: sub_path.to_string(),
-                    files_size: 0,
-                    total_size: 0,
-                    children: vec![],
-                    parent: curr_index,
-                };
-                folders.push(new_folder);
-                let new_index = folders.len() - 1;
-                folders[curr_index].children.push(new_index);
-                curr_index = new_index;
+                curr_index = child_folder(&mut folders, curr_index, sub_path);
+            },
+            ["dir", sub_path] => {
+                child_folder(&mut folders, curr_index, sub_path);
             },
-            [size_str, _] => {
+            [size_str, name] => {
                 if let Ok(size) = size_str.parse::<u128>() {
-                    folders[curr_index].files_size += size;
+                    // Listing the same folder twice must not count its files twice
+                    let files = &mut folders[curr_index].files;
+                    match files.iter_mut().find(|file| file.name == name) {
+                        Some(file) => file.size = size,
+                        None => files.push(File { name: name.to_string(), size }),
+                    }
                 }
             },
             _ => {}
         }
     }
 
+    for folder in &mut folders {
+        folder.files_size = folder.files.iter().map(|file| file.size).sum();
+    }
     calc_folder_total_size(&mut folders, 0);
-    // print_folder_tree(&folders, 0, 0);
     Some(folders)
 }
 
+fn folder_path(folders: &[Folder], index: usize) -> String {
+    if index == 0 {
+        return "/".to_string();
+    }
+
+    let parent_path = folder_path(folders, folders[index].parent);
+    let separator = if parent_path.ends_with('/') { "" } else { "/" };
+
+    format!("{parent_path}{separator}{}", folders[index].name)
+}
+
+/// Finds the folder or file at an absolute path, like `/a/e/i`.
+fn lookup<'a>(folders: &'a [Folder], path: &str) -> Option<Entry<'a>> {
+    let path = path.strip_prefix('/')?;
+    let mut components = path.split('/').filter(|component| !component.is_empty()).peekable();
+    let mut curr_index = 0;
+
+    while let Some(component) = components.next() {
+        let folder = &folders[curr_index];
+        if let Some(&index) = folder.children.iter().find(|&&index| folders[index].name == component) {
+            curr_index = index;
+        } else if components.peek().is_none() {
+            return folder.files.iter().find(|file| file.name == component).map(Entry::File);
+        } else {
+            return None;
+        }
+    }
+
+    Some(Entry::Folder(curr_index))
+}
+
+/// Total size and path of every folder, subfolders before their parent, like `du`.
+fn render_du(folders: &[Folder], index: usize) -> String {
+    let mut report: String = folders[index]
+        .children
+        .iter()
+        .map(|&child_index| render_du(folders, child_index))
+        .collect();
+    report += &format!("{}\t{}\n", folders[index].total_size, folder_path(folders, index));
+
+    report
+}
+
+/// Folders and files sorted by name, in the format used by the puzzle description.
+fn render_tree(folders: &[Folder], index: usize, depth: usize) -> String {
+    let folder = &folders[index];
+    let indent = "  ".repeat(depth);
+    let mut tree = format!("{indent}- {} (dir, size={})\n", folder.name, folder.total_size);
+
+    let subfolders = folder.children.iter().map(|&child_index| (&folders[child_index].name, Some(child_index), 0));
+    let files = folder.files.iter().map(|file| (&file.name, None, file.size));
+
+    for (name, child_index, size) in subfolders.chain(files).sorted_by_key(|(name, _, _)| *name) {
+        match child_index {
+            Some(child_index) => tree += &render_tree(folders, child_index, depth + 1),
+            None => tree += &format!("{indent}  - {name} (file, size={size})\n"),
+        }
+    }
+
+    tree
+}
+
 fn calc_folder_total_size(folders: &mut [Folder], index: usize) {
     if folders[index].total_size != 0 {
         return;
@@ -102,12 +195,123 @@ fn find_sum_of_small_folder_sizes(folders: &[Folder], index: usize, max_size: u1
     own_contribution + child_contributions
 }
 
+#[derive(Eq, PartialEq, Debug)]
+struct Deletion {
+    folders: Vec<usize>,
+    freed: u128,
+}
+
+#[derive(Eq, PartialEq, Debug)]
+struct CleanupPlan {
+    to_free: u128,
+    smallest_folder: Option<Deletion>,
+    // Folders that are not inside one another, freeing as little as possible beyond to_free
+    best_set: Option<Deletion>,
+}
+
+/// Folders in pre-order, along with the position right after each folder's subtree.
+fn pre_order(folders: &[Folder], index: usize, order: &mut Vec<usize>, subtree_ends: &mut Vec<usize>) {
+    let position = order.len();
+    order.push(index);
+    subtree_ends.push(0);
+
+    for &child_index in &folders[index].children {
+        pre_order(folders, child_index, order, subtree_ends);
+    }
+
+    subtree_ends[position] = order.len();
+}
+
+/// Branch and bound over the folders in pre-order, where each one is either deleted, skipping its
+/// subtree, or kept, going into it.
+struct DeletionSearch<'a> {
+    folders: &'a [Folder],
+    order: Vec<usize>,
+    subtree_ends: Vec<usize>,
+    // Most that can be freed from each position on, by deleting the folders whose subtrees cover the rest
+    most_freed: Vec<u128>,
+    to_free: u128,
+    chosen: Vec<usize>,
+    best: Option<Deletion>,
+}
+
+impl DeletionSearch<'_> {
+    fn search(&mut self, position: usize, freed: u128) {
+        if freed >= self.to_free {
+            // Deleting more would only free more
+            self.best = Some(Deletion { folders: self.chosen.clone(), freed });
+            return;
+        }
+        if position == self.order.len() || freed + self.most_freed[position] < self.to_free {
+            return;
+        }
+
+        let index = self.order[position];
+        let deleted = freed + self.folders[index].total_size;
+        if self.best.as_ref().is_none_or(|best| deleted < best.freed) {
+            self.chosen.push(index);
+            self.search(self.subtree_ends[position], deleted);
+            self.chosen.pop();
+        }
+
+        // Nothing can beat freeing exactly what is needed
+        if self.best.as_ref().is_none_or(|best| best.freed > self.to_free) {
+            self.search(position + 1, freed);
+        }
+    }
+}
+
+/// Smallest total size of at least `to_free` over folders not inside one another, never above `max_size`.
+///
+/// Only sets freeing less than the best one found so far, and that can still free enough, are explored,
+/// so the work doesn't depend on how large the sizes are.
+fn find_best_deletion_set(folders: &[Folder], to_free: u128, max_size: u128) -> Option<Deletion> {
+    let (mut order, mut subtree_ends) = (vec![], vec![]);
+    pre_order(folders, 0, &mut order, &mut subtree_ends);
+
+    let mut most_freed = vec![0; order.len() + 1];
+    for position in (0..order.len()).rev() {
+        most_freed[position] = folders[order[position]].total_size + most_freed[subtree_ends[position]];
+    }
+
+    let mut search = DeletionSearch { folders, order, subtree_ends, most_freed, to_free, chosen: vec![], best: None };
+    search.search(0, 0);
+
+    search.best.filter(|best| best.freed <= max_size)
+}
+
+fn plan_cleanup(folders: &[Folder], capacity: u128, required_free: u128) -> CleanupPlan {
+    let to_free = required_free.saturating_sub(capacity.saturating_sub(folders[0].total_size));
+
+    let smallest_folder = folders
+        .iter()
+        .enumerate()
+        .filter(|(_, folder)| folder.total_size >= to_free)
+        .min_by_key(|(_, folder)| folder.total_size)
+        .map(|(index, folder)| Deletion { folders: vec![index], freed: folder.total_size });
+
+    // The smallest folder is always a valid set, so the best set can't free more
+    let best_set = smallest_folder
+        .as_ref()
+        .and_then(|smallest| find_best_deletion_set(folders, to_free, smallest.freed));
+
+    CleanupPlan { to_free, smallest_folder, best_set }
+}
+
 fn find_min_folder_to_delete(folders: &[Folder]) -> Option<u128> {
-    let target = 30_000_000 - (70_000_000 as u128 - folders[0].total_size);
-    folders.iter()
-        .map(|folder| folder.total_size)
-        .filter(|size| size >= &target)
-        .min()
+    plan_cleanup(folders, 70_000_000, 30_000_000)
+        .smallest_folder
+        .map(|deletion| deletion.freed)
+}
+
+fn render_deletion(folders: &[Folder], deletion: &Option<Deletion>) -> String {
+    match deletion {
+        Some(deletion) => {
+            let paths = deletion.folders.iter().map(|&index| folder_path(folders, index)).join(", ");
+            format!("{} ({paths})", deletion.freed)
+        }
+        None => "not possible".to_string(),
+    }
 }
 
 fn main() {
@@ -115,6 +319,30 @@ fn main() {
     let folders = parse_terminal_output(input_txt).expect("Could not parse input text");
     println!("{}", find_sum_of_small_folder_sizes(&folders, 0, 100_000));
     println!("{}", find_min_folder_to_delete(&folders).expect("Could not find a folder of size at least 30000000"));
+
+    let args = std::env::args().skip(1).collect_vec();
+    match args.iter().map(String::as_str).collect_vec()[..] {
+        [] => {}
+        ["du"] => print!("{}", render_du(&folders, 0)),
+        ["tree"] => print!("{}", render_tree(&folders, 0, 0)),
+        ["find", path] => match lookup(&folders, path) {
+            Some(Entry::Folder(index)) => println!("{} (dir, size={})", folder_path(&folders, index), folders[index].total_size),
+            Some(Entry::File(file)) => println!("{path} (file, size={})", file.size),
+            None => println!("{path} not found"),
+        },
+        ["plan", capacity, required_free] => {
+            let (Ok(capacity), Ok(required_free)) = (capacity.parse(), required_free.parse()) else {
+                println!("Capacity and required free space should be sizes");
+                return;
+            };
+            let plan = plan_cleanup(&folders, capacity, required_free);
+
+            println!("To free: {}", plan.to_free);
+            println!("Smallest folder: {}", render_deletion(&folders, &plan.smallest_folder));
+            println!("Best set: {}", render_deletion(&folders, &plan.best_set));
+        }
+        _ => println!("Usage: day07 [du | tree | find <path> | plan <capacity> <required free>]"),
+    }
 }
 
 #[cfg(test)]
@@ -151,6 +379,48 @@ mod tests {
         assert!(itertools::any(folders.clone(), |folder| folder.name == "/" && folder.total_size == 48_381_165 as u128));
     }
 
+    #[test]
+    fn test_folder_tree_render() {
+        let input = include_str!("test_input1.txt");
+        let folders = parse_terminal_output(input).expect("Could not parse input text");
+
+        insta::assert_snapshot!("test_input1_tree", render_folder_tree(&folders, 0, 0));
+    }
+
+    #[test]
+    fn test_reports() {
+        let input = include_str!("test_input1.txt");
+        let folders = parse_terminal_output(input).expect("Could not parse input text");
+
+        insta::assert_snapshot!("test_input1_du", render_du(&folders, 0));
+        insta::assert_snapshot!("test_input1_puzzle_tree", render_tree(&folders, 0, 0));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let input = include_str!("test_input1.txt");
+        let folders = parse_terminal_output(input).expect("Could not parse input text");
+
+        let Some(Entry::Folder(e)) = lookup(&folders, "/a/e/") else { panic!("/a/e should be a folder") };
+        assert_eq!("/a/e", folder_path(&folders, e));
+        assert_eq!(Some(Entry::Folder(0)), lookup(&folders, "/"));
+        assert_eq!(Some(Entry::File(&File { name: "i".to_string(), size: 584 })), lookup(&folders, "/a/e/i"));
+        assert_eq!(None, lookup(&folders, "/a/x"));
+        assert_eq!(None, lookup(&folders, "/b.txt/x"));
+        assert_eq!(None, lookup(&folders, "a"));
+    }
+
+    #[test]
+    fn test_revisits() {
+        let input = include_str!("test_input1.txt");
+        let revisited = format!("{input}\n$ cd /\n$ cd a\n$ ls\ndir e\n29116 f\n$ cd e\n$ ls\n584 i\n$ cd /\n$ cd x\n$ ls\n10 y");
+        let folders = parse_terminal_output(&revisited).expect("Could not parse input text");
+
+        assert_eq!(5, folders.len());
+        assert_eq!(48_381_165 + 10, folders[0].total_size);
+        assert_eq!(1, folders.iter().filter(|folder| folder.name == "e").count());
+    }
+
     #[test]
     fn test_find_folder_for_deletion() {
         let input = include_str!("test_input1.txt");
@@ -164,6 +434,40 @@ mod tests {
         assert_eq!(24933642, min);
     }
 
+    #[test]
+    fn test_cleanup_plan() {
+        let input = include_str!("test_input1.txt");
+        let folders = parse_terminal_output(input).expect("Could not parse input text");
+        let index_of = |path| match lookup(&folders, path) {
+            Some(Entry::Folder(index)) => index,
+            _ => panic!("{path} should be a folder"),
+        };
+
+        let plan = plan_cleanup(&folders, 70_000_000, 30_000_000);
+        assert_eq!(8_381_165, plan.to_free);
+        assert_eq!(Some(Deletion { folders: vec![index_of("/d")], freed: 24_933_642 }), plan.smallest_folder);
+        assert_eq!(plan.smallest_folder, plan.best_set);
+
+        // /a and /d together are the only way to free enough without deleting /
+        let plan = plan_cleanup(&folders, 48_381_165, 25_000_000);
+        assert_eq!(Some(Deletion { folders: vec![0], freed: 48_381_165 }), plan.smallest_folder);
+        assert_eq!(Some(Deletion { folders: vec![index_of("/a"), index_of("/d")], freed: 25_028_495 }), plan.best_set);
+
+        let plan = plan_cleanup(&folders, 100_000_000, 30_000_000);
+        assert_eq!(0, plan.to_free);
+        assert_eq!(Some(Deletion { folders: vec![], freed: 0 }), plan.best_set);
+
+        // Sizes far beyond the disk don't cost any more
+        let plan = plan_cleanup(&folders, 70_000_000, 60_000_000);
+        assert_eq!(Some(Deletion { folders: vec![0], freed: 48_381_165 }), plan.best_set);
+        let plan = plan_cleanup(&folders, u128::MAX, u128::MAX - 48_381_165 + 25_000_000);
+        assert_eq!(Some(Deletion { folders: vec![index_of("/a"), index_of("/d")], freed: 25_028_495 }), plan.best_set);
+
+        let plan = plan_cleanup(&folders, 48_381_165, 50_000_000);
+        assert_eq!(None, plan.smallest_folder);
+        assert_eq!(None, plan.best_set);
+    }
+
     #[test]
     fn test_first_half() {
         let input = include_str!("test_input1.txt");
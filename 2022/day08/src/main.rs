@@ -1,115 +1,48 @@
-use std::cmp::max;
-
-fn _print_sightline_maps(matrix: &Vec<Vec<[u32; 4]>>) {
-    for k in 0..4 {
-        match k {
-            0 => println!("North"),
-            1 => println!("East"),
-            2 => println!("South"),
-            3 => println!("West"),
-            _ => panic!("k should not reach this value"),
-        }
-
-        for i in 0..matrix.len() {
-            for j in 0..matrix[0].len() {
-                print!("{}", matrix[i][j][k]);
-            }
-            println!("");
-        }
-        println!("");
-    }
-    println!("");
-}
+use std::fs::File;
+use std::io::BufWriter;
 
-fn _print_map(matrix: &Vec<Vec<u32>>) {
-    println!("Map");
-
-    for row in matrix.iter() {
-        for el in row.iter() {
-            print!("{}", el);
-        }
-        println!("");
-    }
-    println!("");
-}
-
-fn map_input_to_matrix(input: &str) -> Vec<Vec<u32>> {
-    input
+/// Heights are single digits, unless a row contains spaces or commas, then they are
+/// separated numbers of any size. Every row must have the same number of trees.
+fn map_input_to_matrix(input: &str) -> Result<Vec<Vec<u32>>, String> {
+    let map = input
         .lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| c.to_digit(10).expect("Expected a digit"))
-                .collect::<Vec<u32>>()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(i, line)| -> Result<Vec<u32>, String> {
+            if line.contains([' ', ',']) {
+                line.split([' ', ','])
+                    .filter(|height| !height.is_empty())
+                    .map(|height| height.parse().map_err(|err| format!("Invalid height {height} on row {}: {err}", i + 1)))
+                    .collect()
+            } else {
+                line.chars()
+                    .map(|c| c.to_digit(10).ok_or(format!("Expected a digit on row {}, got {c}", i + 1)))
+                    .collect()
+            }
         })
-        .collect::<Vec<Vec<u32>>>()
-}
-
-fn calc_sightlines(map: &Vec<Vec<u32>>) -> Vec<Vec<[u32; 4]>> {
-    let height = map.len();
-    let width = map[0].len();
-    let mut sightlines_map = vec![vec![[0, 0, 0, 0 as u32]; width - 2]; height - 2];
-
-    for j in 0..width - 2 {
-        // north
-        sightlines_map[0][j][0] = map[0][j+1];
-        // south
-        sightlines_map[height - 3][j][2] = map[height - 1][j+1];
-    }
-
-    for i in 1..height - 2 {
-        for j in 0..width - 2 {
-            // north
-            sightlines_map[i][j][0] = max(map[i][j+1], sightlines_map[i - 1][j][0]);
-            // south
-            sightlines_map[height - i - 3][j][2] =
-                max(map[height - i - 1][j+1], sightlines_map[height - i - 2][j][2]);
-        }
-    }
+        .collect::<Result<Vec<Vec<u32>>, String>>()?;
 
-    for i in 0..height - 2 {
-        // east
-        sightlines_map[i][width - 3][1] = map[i+1][width - 1];
-        // west
-        sightlines_map[i][0][3] = map[i+1][0];
-    }
-
-    for i in 0..height - 2 {
-        for j in 1..width - 2 {
-            // east
-            sightlines_map[i][width - j - 3][1] =
-                max(map[i+1][width - j - 1], sightlines_map[i][width - j - 2][1]);
-            // west
-            sightlines_map[i][j][3] = max(map[i+1][j], sightlines_map[i][j - 1][3]);
+    if let Some(width) = map.first().map(Vec::len) {
+        if let Some((i, row)) = map.iter().enumerate().find(|(_, row)| row.len() != width) {
+            return Err(format!("Row {} has {} trees, but the first row has {width}", i + 1, row.len()));
         }
     }
 
-    sightlines_map
+    Ok(map)
 }
 
-fn find_visible_tree_count(map: &Vec<Vec<u32>>) -> u32 {
-    let height = map.len();
-    let width = map[0].len();
-
-    // Each position on the map is 4 numbers. It's the highest tree encountered
-    // in the north, east, south, west direction, respectivly
-    // Includes only the inner rectangle, trees on the edges which are always visible and
-    // as such don't need to be checked
-    let sightlines_map = calc_sightlines(&map);
-
-    // Init with edges, subtract 4 to remove double counting
-    let mut count = 2 * height as u32 + 2 * width as u32 - 4;
-    for i in 1..height - 1 {
-        for j in 1..width - 1 {
-            if &map[i][j] > sightlines_map[i - 1][j - 1].iter().min().unwrap_or(&10) {
-                count += 1;
-            }
-        }
-    }
-
-    count
+fn find_visible_tree_count(map: &[Vec<u32>]) -> u32 {
+    calc_tree_views(map)
+        .iter()
+        .flatten()
+        .filter(|view| view.visible_from.contains(&true))
+        .count() as u32
 }
 
-fn calc_tree_scenic_score(map: &Vec<Vec<u32>>, x: usize, y: usize) -> u32 {
+// Brute force version, kept as the reference for calc_tree_views in tests
+#[cfg(test)]
+fn calc_tree_scenic_score(map: &[Vec<u32>], x: usize, y: usize) -> u64 {
     let height = map.len();
     let width = map[0].len();
     let mut score: usize = 1;
@@ -155,31 +88,226 @@ fn calc_tree_scenic_score(map: &Vec<Vec<u32>>, x: usize, y: usize) -> u32 {
 
     // println!("{} has score {}", map[x][y], score);
 
-    score as u32
+    score as u64
 }
 
-fn find_max_scenic_score(map: &Vec<Vec<u32>>) -> u32 {
-    map
+const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+/// What a tree sees in the north, east, south, west direction, respectively.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct TreeView {
+    // Every tree in that direction is shorter, up to the edge
+    visible_from: [bool; 4],
+    // Distance to the first tree at least as tall, or to the edge
+    distances: [u32; 4],
+}
+
+impl TreeView {
+    // Distances can reach the size of the map, so their product needs more than 32 bits
+    fn scenic_score(&self) -> u64 {
+        self.distances.iter().map(|&distance| u64::from(distance)).product()
+    }
+}
+
+/// Uses a monotonic stack per row and column, so the whole map takes linear time.
+fn calc_tree_views(map: &[Vec<u32>]) -> Vec<Vec<TreeView>> {
+    let height = map.len();
+    let width = map.first().map_or(0, Vec::len);
+    let mut views = vec![vec![TreeView::default(); width]; height];
+
+    // Walks a line of trees, with position(k) being the coordinates of the k-th tree along it
+    let mut walk_line = |len: usize, direction: usize, position: &dyn Fn(usize) -> (usize, usize)| {
+        // Trees that can still block the view of the next ones, strictly decreasing in height
+        let mut blockers: Vec<usize> = Vec::with_capacity(len);
+
+        for k in 0..len {
+            let (i, j) = position(k);
+            while blockers.last().is_some_and(|&b| {
+                let (bi, bj) = position(b);
+                map[bi][bj] < map[i][j]
+            }) {
+                blockers.pop();
+            }
+
+            // Without a blocker, the view reaches the edge
+            let view = &mut views[i][j];
+            view.visible_from[direction] = blockers.is_empty();
+            view.distances[direction] = (k - blockers.last().copied().unwrap_or(0)) as u32;
+            blockers.push(k);
+        }
+    };
+
+    for j in 0..width {
+        walk_line(height, 0, &|k| (k, j));
+        walk_line(height, 2, &|k| (height - 1 - k, j));
+    }
+    for i in 0..height {
+        walk_line(width, 3, &|k| (i, k));
+        walk_line(width, 1, &|k| (i, width - 1 - k));
+    }
+
+    views
+}
+
+fn find_max_scenic_score(map: &[Vec<u32>]) -> u64 {
+    calc_tree_views(map)
         .iter()
-        .enumerate()
-        .map(|(x, row)| 
-            row
-                .iter()
-                .enumerate()
-                .map(|(y, _)| calc_tree_scenic_score(map, x, y))
-                .max()
-                .unwrap()
-        )
+        .flatten()
+        .map(TreeView::scenic_score)
         .max()
-        .unwrap()
+        .unwrap_or(0)
+}
+
+fn render_csv(map: &[Vec<u32>], views: &[Vec<TreeView>]) -> String {
+    let mut csv = format!(
+        "row,column,height,{},{},scenic_score\n",
+        DIRECTIONS.map(|direction| format!("visible_from_{direction}")).join(","),
+        DIRECTIONS.map(|direction| format!("distance_{direction}")).join(","),
+    );
+
+    for (i, row) in views.iter().enumerate() {
+        for (j, view) in row.iter().enumerate() {
+            csv += &format!(
+                "{i},{j},{},{},{},{}\n",
+                map[i][j],
+                view.visible_from.map(|visible| visible.to_string()).join(","),
+                view.distances.map(|distance| distance.to_string()).join(","),
+                view.scenic_score()
+            );
+        }
+    }
+
+    csv
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    Scenic,
+    Visibility, // number of directions a tree is visible from
+}
+
+impl Metric {
+    fn values(self, views: &[Vec<TreeView>]) -> Vec<Vec<u64>> {
+        views
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|view| match self {
+                        Metric::Scenic => view.scenic_score(),
+                        Metric::Visibility => view.visible_from.iter().filter(|&&visible| visible).count() as u64,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Dark blue for zero, through red, to yellow for the highest value.
+/// Log scaled, so that the few very high scenic scores don't wash out the rest of the map.
+fn heat_colour(value: u64, max_value: u64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 3] = [[16.0, 16.0, 96.0], [220.0, 32.0, 32.0], [255.0, 230.0, 0.0]];
+
+    let t = if max_value == 0 { 0.0 } else { (value as f64).ln_1p() / (max_value as f64).ln_1p() * 2.0 };
+    let (from, to) = if t < 1.0 { (STOPS[0], STOPS[1]) } else { (STOPS[1], STOPS[2]) };
+    let t = if t < 1.0 { t } else { t - 1.0 };
+
+    [0, 1, 2].map(|c| (from[c] + (to[c] - from[c]) * t).round() as u8)
+}
+
+/// Tree heights on a background coloured by value, using 24 bit ANSI colours.
+fn render_terminal_heatmap(map: &[Vec<u32>], values: &[Vec<u64>]) -> String {
+    let max_value = values.iter().flatten().copied().max().unwrap_or(0);
+    // Heights are right aligned on the widest one, with a space before it
+    let width = map.iter().flatten().max().map_or(0, |height| height.to_string().len()) + 1;
+    let mut heatmap = String::new();
+
+    for (heights, row) in map.iter().zip(values) {
+        for (height, &value) in heights.iter().zip(row) {
+            let [r, g, b] = heat_colour(value, max_value);
+            heatmap += &format!("\x1b[48;2;{r};{g};{b}m{height:>width$}");
+        }
+        heatmap += "\x1b[0m\n";
+    }
+
+    heatmap
+}
+
+/// Each tree becomes a `scale` by `scale` square, coloured by value.
+fn write_png_heatmap(path: &str, values: &[Vec<u64>], scale: usize) -> Result<(), String> {
+    let height = values.len() * scale;
+    let width = values.first().map_or(0, Vec::len) * scale;
+    let max_value = values.iter().flatten().copied().max().unwrap_or(0);
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for row in values {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&value| heat_colour(value, max_value).repeat(scale))
+            .collect();
+        for _ in 0..scale {
+            pixels.extend_from_slice(&line);
+        }
+    }
+
+    let file = File::create(path).map_err(|err| format!("Could not create {path}: {err}"))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|err| format!("Could not write {path}: {err}"))
+}
+
+fn describe_best_tree(map: &[Vec<u32>], views: &[Vec<TreeView>]) -> Option<String> {
+    let ((i, j), view) = views
+        .iter()
+        .enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, view)| ((i, j), view)))
+        .max_by_key(|(_, view)| view.scenic_score())?;
+    let distances = DIRECTIONS
+        .iter()
+        .zip(view.distances)
+        .map(|(direction, distance)| format!("{distance} {direction}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    Some(format!(
+        "Best tree at row {i}, column {j}, of height {}, with score {}: sees {distances}",
+        map[i][j],
+        view.scenic_score()
+    ))
 }
 
 fn main() {
     let input_txt = include_str!("input.txt");
-    let map = map_input_to_matrix(&input_txt);
+    let map = map_input_to_matrix(input_txt).unwrap_or_else(|err| panic!("{err}"));
 
     println!("{}", find_visible_tree_count(&map));
     println!("{}", find_max_scenic_score(&map));
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let views = calc_tree_views(&map);
+    let metric = |name: Option<&String>| match name.map(String::as_str) {
+        Some("visibility") => Metric::Visibility,
+        _ => Metric::Scenic,
+    };
+
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("csv") => print!("{}", render_csv(&map, &views)),
+        Some("heatmap") => {
+            print!("{}", render_terminal_heatmap(&map, &metric(args.get(1)).values(&views)));
+            println!("{}", describe_best_tree(&map, &views).unwrap_or_default());
+        }
+        Some("png") if args.len() >= 2 => {
+            if let Err(err) = write_png_heatmap(&args[1], &metric(args.get(2)).values(&views), 4) {
+                println!("{err}");
+            }
+        }
+        _ => println!("Usage: day08 [csv | heatmap [scenic | visibility] | png <path> [scenic | visibility]]"),
+    }
 }
 
 #[cfg(test)]
@@ -205,25 +333,156 @@ mod tests {
 
     #[test]
     fn test() {
-        let map = map_input_to_matrix(&TEST_INPUT);
+        let map = map_input_to_matrix(TEST_INPUT).unwrap();
         assert_eq!(21, find_visible_tree_count(&map));
     }
 
     #[test]
     fn test_scenic_pick() {
-        let map = map_input_to_matrix(&TEST_INPUT);
+        let map = map_input_to_matrix(TEST_INPUT).unwrap();
         assert_eq!(8, find_max_scenic_score(&map));
     }
 
     #[test]
     fn test2() {
-        let map = map_input_to_matrix(&TEST_INPUT2);
+        let map = map_input_to_matrix(TEST_INPUT2).unwrap();
         assert_eq!(37, find_visible_tree_count(&map));
     }
 
     #[test]
     fn test3() {
-        let map = map_input_to_matrix(&TEST_INPUT3);
+        let map = map_input_to_matrix(TEST_INPUT3).unwrap();
         assert_eq!(28, find_visible_tree_count(&map));
     }
+
+    #[test]
+    fn test_tree_views() {
+        let map = map_input_to_matrix(TEST_INPUT).unwrap();
+        let views = calc_tree_views(&map);
+
+        assert_eq!(TreeView { visible_from: [true, true, false, false], distances: [1, 2, 2, 1] }, views[1][2]);
+        assert_eq!(TreeView { visible_from: [false, false, true, true], distances: [2, 2, 1, 2] }, views[3][2]);
+        assert_eq!(TreeView { visible_from: [true, false, false, true], distances: [0, 2, 2, 0] }, views[0][0]);
+    }
+
+    #[test]
+    fn test_csv() {
+        let map = map_input_to_matrix(TEST_INPUT).unwrap();
+        let csv = render_csv(&map, &calc_tree_views(&map));
+
+        assert_eq!(26, csv.lines().count());
+        assert_eq!(
+            "row,column,height,visible_from_north,visible_from_east,visible_from_south,visible_from_west,distance_north,distance_east,distance_south,distance_west,scenic_score",
+            csv.lines().next().unwrap()
+        );
+        assert_eq!("3,2,5,false,false,true,true,2,2,1,2,8", csv.lines().nth(18).unwrap());
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let map = map_input_to_matrix(TEST_INPUT).unwrap();
+        let views = calc_tree_views(&map);
+
+        assert_eq!([16, 16, 96], heat_colour(0, 8));
+        assert_eq!([255, 230, 0], heat_colour(8, 8));
+        assert_eq!([16, 16, 96], heat_colour(0, 0));
+        assert_eq!(5, render_terminal_heatmap(&map, &Metric::Scenic.values(&views)).lines().count());
+        assert_eq!(
+            Some("Best tree at row 3, column 2, of height 5, with score 8: sees 2 north, 2 east, 1 south, 2 west".to_string()),
+            describe_best_tree(&map, &views)
+        );
+    }
+
+    #[test]
+    fn test_scenic_score_against_brute_force() {
+        let mut seed: u32 = 42;
+        let mut next = |modulo: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) % modulo
+        };
+
+        for _ in 0..50 {
+            let (height, width) = (next(20) as usize + 1, next(20) as usize + 1);
+            // Few distinct heights so that equal heights, which block the view, are common
+            let max_height = next(10) + 1;
+            let map: Vec<Vec<u32>> = (0..height).map(|_| (0..width).map(|_| next(max_height)).collect()).collect();
+            let views = calc_tree_views(&map);
+
+            for (x, row) in views.iter().enumerate() {
+                for (y, view) in row.iter().enumerate() {
+                    assert_eq!(calc_tree_scenic_score(&map, x, y), view.scenic_score(), "{map:?} at {x}, {y}");
+
+                    let tree = map[x][y];
+                    let visible_from = [
+                        (0..x).all(|i| map[i][y] < tree),
+                        (y + 1..width).all(|j| map[x][j] < tree),
+                        (x + 1..height).all(|i| map[i][y] < tree),
+                        (0..y).all(|j| map[x][j] < tree),
+                    ];
+                    assert_eq!(visible_from, view.visible_from, "{map:?} at {x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_grids() {
+        assert_eq!(Ok(vec![]), map_input_to_matrix(""));
+        assert_eq!(0, find_visible_tree_count(&[]));
+        assert_eq!(0, find_max_scenic_score(&[]));
+
+        let map = map_input_to_matrix("5").unwrap();
+        assert_eq!(1, find_visible_tree_count(&map));
+        assert_eq!(0, find_max_scenic_score(&map));
+
+        let map = map_input_to_matrix("30373").unwrap();
+        assert_eq!(5, find_visible_tree_count(&map));
+        assert_eq!(0, find_max_scenic_score(&map));
+
+        let map = map_input_to_matrix("3\n0\n3\n7").unwrap();
+        assert_eq!(4, find_visible_tree_count(&map));
+
+        let map = map_input_to_matrix("12\n34").unwrap();
+        assert_eq!(4, find_visible_tree_count(&map));
+        assert_eq!(0, find_max_scenic_score(&map));
+    }
+
+    #[test]
+    fn test_separated_heights() {
+        let map = map_input_to_matrix("30 0 30 70 30\n20 50 50 10 20\n60 50 30 30 20\n30 30 50 40 90\n30 50 30 90 0").unwrap();
+        let values: Vec<Vec<u64>> = map.iter().map(|row| row.iter().map(|&height| u64::from(height)).collect()).collect();
+        let heatmap = render_terminal_heatmap(&map, &values);
+        assert!(heatmap.starts_with("\x1b[48;2;238;135;15m 30\x1b[48;2;16;16;96m  0"), "{heatmap:?}");
+        assert_eq!(map_input_to_matrix(TEST_INPUT).unwrap().iter().map(|row| row.iter().map(|h| h * 10).collect()).collect::<Vec<Vec<u32>>>(), map);
+        assert_eq!(21, find_visible_tree_count(&map));
+        assert_eq!(8, find_max_scenic_score(&map));
+
+        assert_eq!(Ok(vec![vec![100, 200], vec![3, 4000]]), map_input_to_matrix("100,200\n3, 4000\n"));
+        let heatmap = render_terminal_heatmap(&[vec![100, 200], vec![3, 4000]], &[vec![0, 0], vec![0, 0]]);
+        assert_eq!("\x1b[48;2;16;16;96m    3\x1b[48;2;16;16;96m 4000\x1b[0m", heatmap.lines().nth(1).unwrap());
+    }
+
+    #[test]
+    fn test_large_scenic_score() {
+        // A single tall tree in the middle sees 300 trees in every direction
+        let mut map = vec![vec![0; 601]; 601];
+        map[300][300] = 1;
+        assert_eq!(300_u64.pow(4), find_max_scenic_score(&map));
+    }
+
+    #[test]
+    fn test_surrounding_whitespace() {
+        let expected = map_input_to_matrix(TEST_INPUT).unwrap();
+        assert_eq!(Ok(expected.clone()), map_input_to_matrix(&TEST_INPUT.replace('\n', "\r\n")));
+        assert_eq!(Ok(expected.clone()), map_input_to_matrix(&TEST_INPUT.replacen('\n', " \n", 1)));
+        assert_eq!(Ok(expected), map_input_to_matrix(&format!("\t{TEST_INPUT}  \n")));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(map_input_to_matrix("303\n25\n653").is_err());
+        assert!(map_input_to_matrix("1 2 3\n4 5").is_err());
+        assert!(map_input_to_matrix("3a3\n255\n653").is_err());
+        assert!(map_input_to_matrix("1 -2\n4 5").is_err());
+    }
 }
@@ -1,15 +1,34 @@
 use std::cmp::{min, max};
 use std::collections::HashSet;
+use std::hash::Hash;
 use std::str::FromStr;
 use std::io::Error;
 use std::io::ErrorKind::InvalidInput;
 
-#[derive(Debug, PartialEq, Eq)]
-struct ParseError;
-
+// F and B move forward and back along the third axis, for 3D ropes
 #[derive(Debug, PartialEq, Eq)]
 enum Direction {
-    R, U, L, D
+    R, U, L, D,
+    UR, UL, DR, DL,
+    F, B,
+}
+
+impl Direction {
+    /// Unit move along the up, right and forward axes, respectively.
+    fn delta(&self) -> [i32; 3] {
+        match self {
+            Direction::R => [0, 1, 0],
+            Direction::U => [1, 0, 0],
+            Direction::L => [0, -1, 0],
+            Direction::D => [-1, 0, 0],
+            Direction::UR => [1, 1, 0],
+            Direction::UL => [1, -1, 0],
+            Direction::DR => [-1, 1, 0],
+            Direction::DL => [-1, -1, 0],
+            Direction::F => [0, 0, 1],
+            Direction::B => [0, 0, -1],
+        }
+    }
 }
 
 impl FromStr for Direction {
@@ -21,6 +40,12 @@ impl FromStr for Direction {
             "U" => Ok(Direction::U),
             "L" => Ok(Direction::L),
             "D" => Ok(Direction::D),
+            "UR" | "RU" => Ok(Direction::UR),
+            "UL" | "LU" => Ok(Direction::UL),
+            "DR" | "RD" => Ok(Direction::DR),
+            "DL" | "LD" => Ok(Direction::DL),
+            "F" => Ok(Direction::F),
+            "B" => Ok(Direction::B),
             _ => Err(Error::new(InvalidInput, "Invalid direction"))
         }
     }
@@ -55,7 +80,7 @@ fn adjust_tail_pos_based_on_head(tail: &mut Position, head: &Position) {
     let x = tail.1 - head.1;
 
     if (-1..=1).contains(&y) && (-1..=1).contains(&x) {
-        return
+        // Touching, nothing to do
     } 
     else if x.abs() == y.abs() {
         tail.0 -= if y.is_negative() { y + 1 } else { y - 1 };
@@ -71,31 +96,191 @@ fn adjust_tail_pos_based_on_head(tail: &mut Position, head: &Position) {
     }
 }
 
-fn calc_position_count(moves: &Vec<Move>) -> u32 {
-    let mut set = HashSet::new();
-    let mut head = Position(0, 0);
-    let mut tail = Position(0, 0);
+/// Position with a third, forward axis.
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+struct Position3(i32, i32, i32);
+
+trait Knot: Copy + Eq + Hash {
+    const DIMENSIONS: usize;
+
+    fn origin() -> Self;
+
+    /// Moves along the first DIMENSIONS axes of the delta, see check_moves for the others.
+    fn offset(&mut self, delta: [i32; 3]);
+
+    fn follow(&mut self, head: &Self);
+
+    /// Coordinates for exports, x grows to the right, y up and z forward.
+    fn coordinates(&self) -> Vec<i32>;
+}
 
-    set.insert(tail);
+impl Knot for Position {
+    const DIMENSIONS: usize = 2;
+
+    fn origin() -> Self {
+        Position(0, 0)
+    }
+
+    fn offset(&mut self, delta: [i32; 3]) {
+        self.0 += delta[0];
+        self.1 += delta[1];
+    }
+
+    fn follow(&mut self, head: &Self) {
+        adjust_tail_pos_based_on_head(self, head);
+    }
+
+    fn coordinates(&self) -> Vec<i32> {
+        vec![self.1, self.0]
+    }
+}
+
+impl Knot for Position3 {
+    const DIMENSIONS: usize = 3;
+
+    fn origin() -> Self {
+        Position3(0, 0, 0)
+    }
 
-    for m in moves {
-        for _ in 0..m.count {
-            match m.direction {
-                Direction::R => head.1 += 1,
-                Direction::U => head.0 += 1,
-                Direction::L => head.1 -= 1,
-                Direction::D => head.0 -= 1,
+    fn offset(&mut self, delta: [i32; 3]) {
+        self.0 += delta[0];
+        self.1 += delta[1];
+        self.2 += delta[2];
+    }
+
+    // Same rule as in 2D: once not touching, take one step on every axis where the head is elsewhere
+    fn follow(&mut self, head: &Self) {
+        let diffs = [head.0 - self.0, head.1 - self.1, head.2 - self.2];
+
+        if diffs.iter().any(|diff| diff.abs() > 1) {
+            self.offset(diffs.map(i32::signum));
+        }
+    }
+
+    fn coordinates(&self) -> Vec<i32> {
+        vec![self.1, self.0, self.2]
+    }
+}
+
+/// Errors on the first move that leaves the space of the knots, like moving forward in 2D.
+fn check_moves<K: Knot>(moves: &[Move]) -> Result<(), Error> {
+    for (index, m) in moves.iter().enumerate() {
+        if m.direction.delta()[K::DIMENSIONS..].iter().any(|&axis| axis != 0) {
+            let message = format!("Move {} ({:?}) needs more than {} dimensions", index + 1, m.direction, K::DIMENSIONS);
+            return Err(Error::new(InvalidInput, message));
+        }
+    }
+
+    Ok(())
+}
+
+/// Rope of knots, the head being the first one, starting on top of each other at the origin.
+struct Rope<K: Knot> {
+    knots: Vec<K>,
+    visited: Vec<HashSet<K>>, // cells visited by each knot
+}
+
+impl<K: Knot> Rope<K> {
+    fn new(len: usize) -> Self {
+        let origin = K::origin();
+
+        Rope {
+            knots: vec![origin; len],
+            visited: vec![HashSet::from([origin]); len],
+        }
+    }
+
+    /// Moves the head by one cell, with every other knot following the one before it.
+    fn step(&mut self, direction: &Direction) {
+        let Some(head) = self.knots.first_mut() else { return };
+        head.offset(direction.delta());
+
+        for curr in 1..self.knots.len() {
+            let prev = self.knots[curr - 1];
+            self.knots[curr].follow(&prev);
+        }
+
+        for (visited, knot) in self.visited.iter_mut().zip(&self.knots) {
+            visited.insert(*knot);
+        }
+    }
+
+    /// Applies the moves one step at a time, yielding the knot positions after each step.
+    fn steps<'a>(&'a mut self, moves: &'a [Move]) -> impl Iterator<Item = Vec<K>> + 'a {
+        moves
+            .iter()
+            .flat_map(|m| std::iter::repeat_n(&m.direction, m.count as usize))
+            .map(move |direction| {
+                self.step(direction);
+                self.knots.clone()
+            })
+    }
+
+    fn tail_visited_count(&self) -> u32 {
+        self.visited.last().map_or(0, |visited| visited.len() as u32)
+    }
+}
+
+/// Knot positions at every step of a simulation, starting with the initial one.
+struct Trace<K: Knot> {
+    steps: Vec<Vec<K>>,
+    visited: Vec<HashSet<K>>,
+}
+
+impl<K: Knot> Trace<K> {
+    fn record(moves: &[Move], rope_len: usize) -> Self {
+        let mut rope = Rope::new(rope_len);
+        let mut steps = vec![rope.knots.clone()];
+        steps.extend(rope.steps(moves));
+
+        Trace { steps, visited: rope.visited }
+    }
+
+    fn to_csv(&self) -> String {
+        let axes = ["x", "y", "z"][..K::DIMENSIONS].join(",");
+        let mut csv = format!("step,knot,{axes}\n");
+
+        for (step, knots) in self.steps.iter().enumerate() {
+            for (knot, pos) in knots.iter().enumerate() {
+                let coordinates: Vec<String> = pos.coordinates().iter().map(i32::to_string).collect();
+                csv += &format!("{step},{knot},{}\n", coordinates.join(","));
             }
-            
-            adjust_tail_pos_based_on_head(&mut tail, &head);
-            set.insert(tail);
         }
+
+        csv
+    }
+
+    fn to_json(&self) -> String {
+        let points = |positions: &mut dyn Iterator<Item = &K>| {
+            positions.map(|pos| format!("{:?}", pos.coordinates()).replace(' ', "")).collect::<Vec<String>>().join(",")
+        };
+        let steps = self
+            .steps
+            .iter()
+            .map(|knots| format!("[{}]", points(&mut knots.iter())))
+            .collect::<Vec<String>>()
+            .join(",");
+        // Sorted, so that the output does not depend on the hashing
+        let visited = self
+            .visited
+            .iter()
+            .map(|visited| {
+                let mut cells: Vec<&K> = visited.iter().collect();
+                cells.sort_by_key(|pos| pos.coordinates());
+                format!("[{}]", points(&mut cells.into_iter()))
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        format!("{{\"steps\":[{steps}],\"visited\":[{visited}]}}")
     }
+}
 
-    set.len() as u32
+fn calc_position_count(moves: &[Move]) -> u32 {
+    calc_snake_tail_positions_count(moves, 2)
 }
 
-fn print_snake(snake: &Vec<Position>) {
+fn print_snake(snake: &[Position]) {
     // Finds max and min height and width to form bounds of the box to be drawn
     let min_h = min(snake.iter().map(|pos| pos.0).min().unwrap(), -2);
     let max_h = max(snake.iter().map(|pos| pos.0).max().unwrap(), 2);
@@ -126,61 +311,59 @@ fn print_snake(snake: &Vec<Position>) {
             print!("_");
         }
 
-        println!("");
+        println!();
     }
 
-    println!("");
+    println!();
     std::thread::sleep(std::time::Duration::from_millis(250));
 }
 
-fn calc_snake_tail_positions_count(moves: &Vec<Move>, snake_len: usize) -> u32 {
+fn calc_snake_tail_positions_count(moves: &[Move], snake_len: usize) -> u32 {
     // Head is 0, Tail is len() - 1
     if snake_len < 2 {
         return 0;
     }
 
-    let mut snake = vec![Position(0, 0); snake_len];
-    let mut set = HashSet::new();
-
-    set.insert(snake.last().unwrap().clone());
+    let mut rope = Rope::<Position>::new(snake_len);
+    rope.steps(moves).for_each(drop);
 
-    for m in moves {
-        for _ in 0..m.count {
-            match m.direction {
-                Direction::R => snake[0].1 += 1,
-                Direction::U => snake[0].0 += 1,
-                Direction::L => snake[0].1 -= 1,
-                Direction::D => snake[0].0 -= 1,
-            }
-
-            for (curr, prev) in (0..snake_len-1).zip(1..snake_len) {
-                let curr_clone = snake[curr].clone();
-                adjust_tail_pos_based_on_head(&mut snake[prev], &curr_clone);
-            }
-
-            set.insert(snake.last().unwrap().clone());
-
-            // print_snake(&snake);
-        }
-    }
+    rope.tail_visited_count()
+}
 
-    set.len() as u32
+fn parse_moves(input: &str) -> Vec<Move> {
+    input
+        .lines()
+        .filter_map(|line| line.parse::<Move>().ok())
+        .collect()
 }
 
 fn main() {
     let input = include_str!("input.txt");
-    let moves: Vec<Move> = input
-        .lines()
-        .filter_map(|line| 
-            match line.parse::<Move>() {
-                Ok(r#move) => Some(r#move),
-                Err(_) => None
-            }
-        )
-        .collect();
+    let moves = parse_moves(input);
 
     println!("{}", calc_position_count(&moves));
     println!("{}", calc_snake_tail_positions_count(&moves, 10));
+
+    // Exports can use other moves, which get a 3D rope if they leave the plane
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let moves = match args.get(1) {
+        Some(path) => parse_moves(&std::fs::read_to_string(path).unwrap_or_else(|err| panic!("Could not read {path}: {err}"))),
+        None => moves,
+    };
+    let planar = check_moves::<Position>(&moves).is_ok();
+
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("csv") if planar => print!("{}", Trace::<Position>::record(&moves, 10).to_csv()),
+        Some("csv") => print!("{}", Trace::<Position3>::record(&moves, 10).to_csv()),
+        Some("json") if planar => println!("{}", Trace::<Position>::record(&moves, 10).to_json()),
+        Some("json") => println!("{}", Trace::<Position3>::record(&moves, 10).to_json()),
+        Some("animate") => match check_moves::<Position>(&moves) {
+            Ok(()) => Rope::<Position>::new(10).steps(&moves).for_each(|knots| print_snake(&knots)),
+            Err(err) => println!("Can only animate 2D ropes: {err}"),
+        },
+        Some(_) => println!("Usage: day09 [csv | json | animate] [moves file]"),
+    }
 }
 
 
@@ -220,6 +403,42 @@ mod tests {
         assert_eq!(36, calc_snake_tail_positions_count(&moves, 10));
     }
 
+    #[test]
+    fn test_rope_steps() {
+        let moves = parse_moves(include_str!("test_input.txt"));
+        let mut rope = Rope::new(10);
+        let steps: Vec<Vec<Position>> = rope.steps(&moves).collect();
+
+        assert_eq!(24, steps.len());
+        assert_eq!(vec![Position(0, 1)].into_iter().chain(vec![Position(0, 0); 9]).collect::<Vec<_>>(), steps[0]);
+        // Final state drawn in the puzzle description
+        assert_eq!(
+            &[Position(2, 2), Position(2, 1), Position(2, 2), Position(2, 3), Position(2, 2), Position(1, 1)],
+            &steps[23][..6]
+        );
+        assert!(steps[23][6..].iter().all(|&pos| pos == Position(0, 0)));
+        assert_eq!(1, rope.tail_visited_count());
+
+        let visited: Vec<usize> = rope.visited.iter().map(HashSet::len).collect();
+        assert_eq!(13, visited[1]);
+        assert!(visited.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_trace_export() {
+        let moves = parse_moves("R 2\nU 1");
+        let trace = Trace::<Position>::record(&moves, 2);
+
+        assert_eq!(
+            "step,knot,x,y\n0,0,0,0\n0,1,0,0\n1,0,1,0\n1,1,0,0\n2,0,2,0\n2,1,1,0\n3,0,2,1\n3,1,1,0\n",
+            trace.to_csv()
+        );
+        assert_eq!(
+            "{\"steps\":[[[0,0],[0,0]],[[1,0],[0,0]],[[2,0],[1,0]],[[2,1],[1,0]]],\"visited\":[[[0,0],[1,0],[2,0],[2,1]],[[0,0],[1,0]]]}",
+            trace.to_json()
+        );
+    }
+
     #[test]
     fn check_generality_of_snake_fn() {
         let input = include_str!("test_input.txt");
@@ -235,4 +454,47 @@ mod tests {
 
         assert_eq!(13, calc_snake_tail_positions_count(&moves, 2));
     }
+
+    #[test]
+    fn test_diagonal_moves() {
+        let moves = parse_moves("UR 3\nDL 1\nRD 2");
+        assert_eq!(Direction::DR, moves[2].direction);
+        assert!(check_moves::<Position>(&moves).is_ok());
+
+        let mut rope = Rope::<Position>::new(2);
+        let steps: Vec<Vec<Position>> = rope.steps(&moves).collect();
+        assert_eq!(vec![Position(3, 3), Position(2, 2)], steps[2]);
+        assert_eq!(vec![Position(0, 4), Position(1, 3)], steps[5]);
+        // (0, 0), (1, 1), (2, 2), then (1, 3)
+        assert_eq!(4, rope.tail_visited_count());
+    }
+
+    #[test]
+    fn test_3d_rope() {
+        let moves = parse_moves("R 2\nF 2\nB 4");
+        assert!(check_moves::<Position>(&moves).is_err());
+        assert!(check_moves::<Position3>(&moves).is_ok());
+
+        let trace = Trace::<Position3>::record(&moves, 2);
+        assert_eq!(vec![Position3(0, 2, 2), Position3(0, 2, 1)], trace.steps[4]);
+        assert_eq!(vec![Position3(0, 2, -2), Position3(0, 2, -1)], trace.steps[8]);
+        assert_eq!(5, trace.visited[1].len());
+        assert_eq!("step,knot,x,y,z", trace.to_csv().lines().next().unwrap());
+        assert!(trace.to_json().ends_with("[[0,0,0],[1,0,0],[2,0,-1],[2,0,0],[2,0,1]]]}"));
+    }
+
+    #[test]
+    fn test_3d_matches_2d_on_planar_moves() {
+        for (input, len) in [(include_str!("test_input.txt"), 2), (include_str!("test_input.txt"), 10), (include_str!("test_input2.txt"), 10)] {
+            let moves = parse_moves(input);
+            let mut flat = Rope::<Position>::new(len);
+            let mut spatial = Rope::<Position3>::new(len);
+
+            for (knots, knots3) in flat.steps(&moves).zip(spatial.steps(&moves)) {
+                assert!(knots3.iter().all(|pos| pos.2 == 0));
+                assert_eq!(knots, knots3.iter().map(|pos| Position(pos.0, pos.1)).collect::<Vec<_>>());
+            }
+            assert_eq!(flat.tail_visited_count(), spatial.tail_visited_count());
+        }
+    }
 }
@@ -0,0 +1,243 @@
+use std::io::{BufRead, Write};
+use anyhow::{Result, anyhow};
+
+use super::{Cpu, Program, Registers, Trigger, get_display_pixels, render_display};
+
+const HELP: &str = "\
+step [n]           run n cycles, 1 by default
+continue           run until a breakpoint fires or the program ends
+run                restart the program and continue
+break [trigger..]  add breakpoints (line:N, cycle:N, x=N or a register name to stop when it changes), or list them
+delete [n]         remove breakpoint n, or all of them
+print [register]   show a register during the current cycle, or all those in use
+screen             show what the CRT has drawn so far
+quit";
+
+/// Interactive debugger, stopping on cycles so their registers and the CRT can be looked at.
+pub struct Debugger<'a> {
+    program: &'a Program,
+    cpu: Cpu<'a>,
+    breakpoints: Vec<Trigger>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Debugger { program, cpu: Cpu::new(program), breakpoints: vec![] }
+    }
+
+    /// Reads commands until `quit` or the end of the input.
+    pub fn repl(&mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        writeln!(output, "Loaded {} instructions, type help for the commands", self.program.lines.len())?;
+
+        let mut lines = input.lines();
+        loop {
+            write!(output, "(day10) ")?;
+            output.flush()?;
+
+            let Some(line) = lines.next().transpose()? else { break };
+            if matches!(line.trim(), "quit" | "q") {
+                break;
+            }
+            match self.execute(&line) {
+                Ok(text) if text.is_empty() => {},
+                Ok(text) => writeln!(output, "{text}")?,
+                Err(err) => writeln!(output, "Error: {err}")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a single command, returning what it shows.
+    pub fn execute(&mut self, command: &str) -> Result<String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+
+        match words[..] {
+            [] => Ok(String::new()),
+            ["step" | "s"] => Ok(self.step(1)),
+            ["step" | "s", cycles] => {
+                let cycles = cycles.parse().map_err(|_| anyhow!("Invalid number of cycles {cycles}"))?;
+                Ok(self.step(cycles))
+            },
+            ["continue" | "c"] => Ok(self.resume()),
+            ["run" | "r"] => {
+                self.cpu = Cpu::new(self.program);
+                Ok(self.resume())
+            },
+            ["break" | "b"] => Ok(self.list_breakpoints()),
+            ["break" | "b", ref triggers @ ..] => {
+                for trigger in triggers {
+                    self.breakpoints.push(trigger.parse()?);
+                }
+                Ok(self.list_breakpoints())
+            },
+            ["delete" | "d"] => {
+                self.breakpoints.clear();
+                Ok("Deleted all breakpoints".to_string())
+            },
+            ["delete" | "d", number] => {
+                let index = number
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|number| (1..=self.breakpoints.len()).contains(number))
+                    .ok_or(anyhow!("No breakpoint {number}"))?;
+                let trigger = self.breakpoints.remove(index - 1);
+                Ok(format!("Deleted breakpoint {number}: {trigger:?}"))
+            },
+            ["print" | "p"] => {
+                let registers = self.registers();
+                Ok(('a'..='z')
+                    .filter(|&name| name == 'x' || registers.get(name) != 0)
+                    .map(|name| format!("{name} = {}", registers.get(name)))
+                    .collect::<Vec<String>>()
+                    .join("\n"))
+            },
+            ["print" | "p", name] => match name.chars().collect::<Vec<char>>()[..] {
+                [name] if Registers::index(name).is_some() => Ok(format!("{name} = {}", self.registers().get(name))),
+                _ => Err(anyhow!("Unknown register {name}")),
+            },
+            ["screen"] => Ok(self.screen()),
+            ["help" | "h"] => Ok(HELP.to_string()),
+            _ => Err(anyhow!("Unknown command {}, type help for the commands", command.trim())),
+        }
+    }
+
+    fn step(&mut self, cycles: usize) -> String {
+        if self.cpu.by_ref().take(cycles).count() < cycles {
+            self.finished()
+        } else {
+            self.status()
+        }
+    }
+
+    fn resume(&mut self) -> String {
+        match self.cpu.run_until(&self.breakpoints) {
+            Some((_, trigger)) => format!("Stopped by {trigger:?}\n{}", self.status()),
+            None => self.finished(),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.cpu.line >= self.program.lines.len()
+    }
+
+    /// Registers during the current cycle, or once the program has finished.
+    fn registers(&self) -> Registers {
+        match self.cpu.last {
+            Some(state) if !self.is_finished() => state.registers,
+            _ => self.cpu.registers,
+        }
+    }
+
+    fn pixels(&self) -> Vec<char> {
+        let cycles = self.cpu.last.map_or(0, |state| state.cycle as usize);
+        get_display_pixels(Cpu::new(self.program).take(cycles))
+    }
+
+    fn status(&self) -> String {
+        let Some(state) = self.cpu.last else {
+            return "The program has not started".to_string();
+        };
+        let line = &self.program.lines[state.line];
+        // The CPU moves to the next line once the last cycle of an instruction is done
+        let spent = if self.cpu.line == state.line { self.cpu.spent } else { line.cycles };
+        let pixels = self.pixels();
+        let column = ((state.cycle - 1) % 40) as usize;
+        let row: String = pixels[pixels.len() - column - 1..].iter().collect();
+
+        format!(
+            "cycle {}, line {}: {} (cycle {spent} of {})\nx = {}\ncrt row {}: {row}",
+            state.cycle,
+            state.line + 1,
+            line.text,
+            line.cycles,
+            state.registers.get('x'),
+            (state.cycle - 1) / 40 + 1,
+        )
+    }
+
+    fn finished(&self) -> String {
+        format!("The program has finished after {} cycles, x = {}", self.cpu.cycle - 1, self.cpu.registers.get('x'))
+    }
+
+    fn list_breakpoints(&self) -> String {
+        if self.breakpoints.is_empty() {
+            return "No breakpoints".to_string();
+        }
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .map(|(index, trigger)| format!("{}: {trigger:?}", index + 1))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn screen(&self) -> String {
+        let pixels = self.pixels();
+        if pixels.is_empty() {
+            "Nothing drawn yet".to_string()
+        } else {
+            render_display(&pixels)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InstructionSet;
+
+    #[test]
+    fn test_commands() {
+        let program = InstructionSet::standard().parse_program(include_str!("test_input.txt")).unwrap();
+        let mut debugger = Debugger::new(&program);
+
+        assert_eq!("The program has not started", debugger.execute("step 0").unwrap());
+        assert_eq!("x = 1", debugger.execute("print x").unwrap());
+
+        assert_eq!("cycle 2, line 1: addx 15 (cycle 2 of 2)\nx = 1\ncrt row 1: ##", debugger.execute("step 2").unwrap());
+        assert_eq!("x = 1", debugger.execute("p x").unwrap());
+        assert_eq!("x = 16", debugger.execute("s").unwrap().lines().nth(1).unwrap());
+
+        assert_eq!("1: Cycle(20)\n2: RegisterEquals('x', -100)", debugger.execute("break cycle:20 x=-100").unwrap());
+        let stop = debugger.execute("continue").unwrap();
+        assert!(stop.starts_with("Stopped by Cycle(20)\ncycle 20, line 11: addx -1 (cycle 1 of 2)\nx = 21\n"), "{stop}");
+        assert_eq!(20, debugger.execute("screen").unwrap().len());
+
+        assert_eq!("Deleted breakpoint 1: Cycle(20)", debugger.execute("delete 1").unwrap());
+        assert_eq!("The program has finished after 240 cycles, x = 17", debugger.execute("c").unwrap());
+        assert_eq!("x = 17", debugger.execute("print").unwrap());
+        assert_eq!(render_display(&get_display_pixels(Cpu::new(&program))), debugger.execute("screen").unwrap());
+
+        debugger.execute("b cycle:20").unwrap();
+        assert!(debugger.execute("run").unwrap().contains("\ncycle 20,"));
+
+        assert!(debugger.execute("delete 3").is_err());
+        assert!(debugger.execute("print 1").is_err());
+        assert!(debugger.execute("break line:0").is_err());
+        assert!(debugger.execute("jump").is_err());
+    }
+
+    #[test]
+    fn test_repl() {
+        let program = InstructionSet::standard().parse_program("noop\naddx 3\naddy 2").unwrap();
+        let mut output = vec![];
+
+        Debugger::new(&program).repl("step\nfoo\n\nc\nprint\nquit\nstep\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            "\
+Loaded 3 instructions, type help for the commands
+(day10) cycle 1, line 1: noop (cycle 1 of 1)
+x = 1
+crt row 1: #
+(day10) Error: Unknown command foo, type help for the commands
+(day10) (day10) The program has finished after 5 cycles, x = 4
+(day10) x = 4
+y = 2
+(day10) ",
+            output
+        );
+    }
+}
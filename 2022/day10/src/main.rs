@@ -1,63 +1,261 @@
-use core::str::FromStr;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::fmt::Debug;
 use anyhow::{Result, Error, anyhow};
 
+mod debugger;
+mod ocr;
+
+/// Registers `a` to `z`, all starting at 0 except for `x` which starts at 1.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+struct Registers([i32; 26]);
+
+impl Default for Registers {
+    fn default() -> Self {
+        let mut registers = Registers([0; 26]);
+        registers.set('x', 1);
+        registers
+    }
+}
+
+impl Registers {
+    fn index(name: char) -> Option<usize> {
+        name.is_ascii_lowercase().then(|| (name as u8 - b'a') as usize)
+    }
+
+    fn get(&self, name: char) -> i32 {
+        Registers::index(name).map_or(0, |index| self.0[index])
+    }
+
+    fn set(&mut self, name: char, value: i32) {
+        if let Some(index) = Registers::index(name) {
+            self.0[index] = value;
+        }
+    }
+}
+
+/// Effect of an instruction, applied at the end of its last cycle.
+trait Instruction: Debug {
+    fn execute(&self, registers: &mut Registers);
+}
+
+#[derive(Debug)]
+struct Noop;
+
+impl Instruction for Noop {
+    fn execute(&self, _registers: &mut Registers) {}
+}
+
+#[derive(Debug)]
+struct Add {
+    register: char,
+    value: i32,
+}
+
+impl Instruction for Add {
+    fn execute(&self, registers: &mut Registers) {
+        registers.set(self.register, registers.get(self.register) + self.value);
+    }
+}
+
+// Gets the mnemonic along with the arguments, so one parser can serve a family of instructions
+type Parser = fn(mnemonic: &str, args: &[&str]) -> Result<Box<dyn Instruction>>;
+
+struct InstructionSpec {
+    cycles: u32,
+    parse: Parser,
+}
+
+/// Table of the known instructions, with how many cycles each of them takes.
+struct InstructionSet {
+    specs: HashMap<String, InstructionSpec>,
+}
+
+impl InstructionSet {
+    /// `noop` and `add<register>`, like `addx`, for every register.
+    fn standard() -> Self {
+        let mut set = InstructionSet { specs: HashMap::new() };
+
+        set.define("noop", 1, |_, args| match args {
+            [] => Ok(Box::new(Noop)),
+            _ => Err(anyhow!("noop takes no arguments")),
+        });
+        for register in 'a'..='z' {
+            set.define(&format!("add{register}"), 2, |mnemonic, args| {
+                let register = mnemonic.chars().last().ok_or(anyhow!("Missing register"))?;
+                match args {
+                    [num] => {
+                        let value = num.parse().map_err(|_| anyhow!("Could not parse number component of add op"))?;
+                        Ok(Box::new(Add { register, value }))
+                    },
+                    _ => Err(anyhow!("{mnemonic} takes a single number")),
+                }
+            });
+        }
+
+        set
+    }
+
+    /// Adds or replaces an instruction, which takes at least one cycle.
+    fn define(&mut self, mnemonic: &str, cycles: u32, parse: Parser) {
+        self.specs.insert(mnemonic.to_string(), InstructionSpec { cycles: cycles.max(1), parse });
+    }
+
+    fn parse_program(&self, source: &str) -> Result<Program> {
+        let lines = source
+            .lines()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(index, text)| {
+                let words: Vec<&str> = text.split_whitespace().collect();
+                let spec = self
+                    .specs
+                    .get(words[0])
+                    .ok_or(anyhow!("Unknown instruction {} on line {}", words[0], index + 1))?;
+                let instruction = (spec.parse)(words[0], &words[1..])
+                    .map_err(|err| anyhow!("Invalid instruction on line {}: {err}", index + 1))?;
+
+                Ok(Line { text: text.trim().to_string(), cycles: spec.cycles, instruction })
+            })
+            .collect::<Result<Vec<Line>>>()?;
+
+        Ok(Program { lines })
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    text: String,
+    cycles: u32,
+    instruction: Box<dyn Instruction>,
+}
+
+#[derive(Debug)]
+struct Program {
+    lines: Vec<Line>,
+}
+
+/// Machine state during a cycle, before the instruction finishing in it takes effect.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+struct CycleState {
+    cycle: u64, // starting at 1
+    line: usize, // index of the instruction being executed
+    registers: Registers,
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
-enum Op {
-    Noop, 
-    Addx(i32)
+enum Trigger {
+    Line(usize),
+    Cycle(u64),
+    RegisterEquals(char, i32),
+    RegisterChanges(char),
 }
 
-impl FromStr for Op {
+impl FromStr for Trigger {
     type Err = Error;
 
+    /// `line:N` (1 based), `cycle:N`, `x=N` or a lone register name to stop when it changes.
     fn from_str(s: &str) -> Result<Self> {
-        match s.trim().split_whitespace().collect::<Vec<&str>>().as_slice() {
-            ["noop"] => Ok(Op::Noop),
-            ["addx", num] => {
-                let num: i32 = num.parse().map_err(|_| anyhow!("Could not parse number component of add op"))?;
-                Ok(Op::Addx(num))
+        let number = |num: &str| num.parse::<i64>().map_err(|_| anyhow!("Invalid number in trigger {s}"));
+
+        if let Some(line) = s.strip_prefix("line:") {
+            let line = number(line)?;
+            return (line >= 1).then(|| Trigger::Line(line as usize - 1)).ok_or(anyhow!("Lines start at 1"));
+        }
+        if let Some(cycle) = s.strip_prefix("cycle:") {
+            return Ok(Trigger::Cycle(number(cycle)?.try_into()?));
+        }
+
+        let register_name = |name: &str| match name.chars().collect::<Vec<char>>()[..] {
+            [name] if name.is_ascii_lowercase() => Ok(name),
+            _ => Err(anyhow!("Invalid register in trigger {s}")),
+        };
+        match s.split_once('=') {
+            Some((name, value)) => Ok(Trigger::RegisterEquals(register_name(name)?, number(value)?.try_into()?)),
+            None => Ok(Trigger::RegisterChanges(register_name(s)?)),
+        }
+    }
+}
+
+impl Trigger {
+    fn fires(&self, state: &CycleState, previous: Option<&CycleState>) -> bool {
+        match *self {
+            Trigger::Line(line) => state.line == line && previous.is_none_or(|previous| previous.line != line),
+            Trigger::Cycle(cycle) => state.cycle == cycle,
+            // Only when the register gets the value, otherwise running on would stop every cycle
+            Trigger::RegisterEquals(name, value) => {
+                state.registers.get(name) == value && previous.is_none_or(|previous| previous.registers.get(name) != value)
+            },
+            Trigger::RegisterChanges(name) => {
+                previous.is_some_and(|previous| previous.registers.get(name) != state.registers.get(name))
             },
-            _ => Err(anyhow!("Invalid string as input for parsing Op enum"))
         }
     }
 }
 
-fn calc_register_values_at_cycle(ops: &Vec<Op>) -> Vec<i32> {
-    let mut signals = vec![];
-    let mut x = 1;
+/// Runs a program one cycle at a time, as an iterator over the state during each cycle.
+struct Cpu<'a> {
+    program: &'a Program,
+    registers: Registers,
+    line: usize,
+    cycle: u64,
+    spent: u32, // cycles already spent on the current instruction
+    last: Option<CycleState>, // last state returned
+}
 
-    for op in ops.iter() {
-        match op {
-            Op::Noop => {
-                signals.push(x);
-            },
-            Op::Addx(num) => {
-                signals.push(x);
-                signals.push(x);
-                x += num;
+impl<'a> Cpu<'a> {
+    fn new(program: &'a Program) -> Self {
+        Cpu { program, registers: Registers::default(), line: 0, cycle: 1, spent: 0, last: None }
+    }
+
+    /// Runs until a cycle fires one of the triggers, returning that cycle and the trigger.
+    /// Returns None if the program ends first.
+    fn run_until(&mut self, triggers: &[Trigger]) -> Option<(CycleState, Trigger)> {
+        let mut previous = self.last;
+
+        for state in self.by_ref() {
+            if let Some(trigger) = triggers.iter().find(|trigger| trigger.fires(&state, previous.as_ref())) {
+                return Some((state, *trigger));
             }
+            previous = Some(state);
         }
+
+        None
     }
+}
+
+impl Iterator for Cpu<'_> {
+    type Item = CycleState;
+
+    fn next(&mut self) -> Option<CycleState> {
+        let line = self.program.lines.get(self.line)?;
+        let state = CycleState { cycle: self.cycle, line: self.line, registers: self.registers };
+
+        self.spent += 1;
+        if self.spent == line.cycles {
+            line.instruction.execute(&mut self.registers);
+            self.line += 1;
+            self.spent = 0;
+        }
+        self.cycle += 1;
+        self.last = Some(state);
 
-    signals
+        Some(state)
+    }
 }
 
-fn calc_signal_strengths(register_values: &Vec<i32>) -> Vec<i32> {
-    register_values
-        .iter()
-        .enumerate()
-        .filter(|(cycle, _)| (cycle + 21) % 40 == 0)
-        .map(|(cycle, register_val)| register_val * (cycle + 1) as i32)
+fn calc_signal_strengths(states: impl Iterator<Item = CycleState>) -> Vec<i32> {
+    states
+        .filter(|state| state.cycle % 40 == 20)
+        .map(|state| state.registers.get('x') * state.cycle as i32)
         .collect()
 }
 
-fn get_display_pixels(register_values: &Vec<i32>) -> Vec<char> {
-    register_values
-        .iter()
-        .enumerate()
-        .map(|(cycle, val)| ((cycle + 1) % 40, val))
-        .map(|(cycle, reg_val)|
-            if (reg_val+0..=reg_val+2).contains(&(cycle as i32)) {
+fn get_display_pixels(states: impl Iterator<Item = CycleState>) -> Vec<char> {
+    states
+        .map(|state| (((state.cycle - 1) % 40) as i32, state.registers.get('x')))
+        .map(|(column, reg_val)|
+            if (reg_val-1..=reg_val+1).contains(&column) {
                 '#'
             } else {
                 '.'
@@ -65,18 +263,49 @@ fn get_display_pixels(register_values: &Vec<i32>) -> Vec<char> {
         ).collect()
 }
 
+fn render_display(pixels: &[char]) -> String {
+    pixels
+        .chunks(40)
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 fn main() {
     let input = include_str!("input.txt");
-    let ops: Vec<Op> = input.lines().filter_map(|line| line.parse::<Op>().ok()).collect();
-    let register_values = calc_register_values_at_cycle(&ops);
-    let sum: i32 = calc_signal_strengths(&register_values).iter().sum();
-    
+    let program = InstructionSet::standard().parse_program(input).unwrap_or_else(|err| panic!("{err}"));
+    let sum: i32 = calc_signal_strengths(Cpu::new(&program)).iter().sum();
+
     println!("{}", sum);
 
-    let pixels = get_display_pixels(&register_values);
-    for line in pixels.chunks(40) {
-        let line: String = line.iter().collect();
-        println!("{}", line);
+    // The letters are the answer, the screen is kept for when they can't be read
+    let display = render_display(&get_display_pixels(Cpu::new(&program)));
+    match ocr::decode(&display) {
+        Ok(letters) => println!("{letters}"),
+        Err(err) => println!("Could not read the screen: {err}"),
+    }
+    println!("{display}");
+
+    // Lists every cycle where one of the triggers given as arguments fires
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("break") {
+        let triggers = args[1..].iter().map(|arg| arg.parse()).collect::<Result<Vec<Trigger>>>().unwrap_or_else(|err| panic!("{err}"));
+        let mut cpu = Cpu::new(&program);
+
+        while let Some((state, trigger)) = cpu.run_until(&triggers) {
+            let line = &program.lines[state.line];
+            println!("cycle {:>4}, line {:>3} {:<10} x = {:<4} ({trigger:?})", state.cycle, state.line + 1, line.text, state.registers.get('x'));
+        }
+    }
+
+    // Steps through a program interactively, the puzzle input unless a file is given
+    if args.first().map(String::as_str) == Some("debug") {
+        let source = match args.get(1) {
+            Some(path) => std::fs::read_to_string(path).unwrap_or_else(|err| panic!("Could not read {path}: {err}")),
+            None => input.to_string(),
+        };
+        let program = InstructionSet::standard().parse_program(&source).unwrap_or_else(|err| panic!("{err}"));
+        debugger::Debugger::new(&program).repl(std::io::stdin().lock(), std::io::stdout()).unwrap_or_else(|err| panic!("{err}"));
     }
 }
 
@@ -84,12 +313,98 @@ fn main() {
 mod tests {
     use super::*;
 
+    fn parse(source: &str) -> Program {
+        InstructionSet::standard().parse_program(source).unwrap()
+    }
+
     #[test]
     fn test_first_half() {
-        let input = include_str!("test_input.txt");
-        let ops: Vec<Op> = input.lines().filter_map(|line| line.parse::<Op>().ok()).collect();
-        let register_values = calc_register_values_at_cycle(&ops);
-        let sum = calc_signal_strengths(&register_values).iter().sum();
+        let program = parse(include_str!("test_input.txt"));
+        let sum: i32 = calc_signal_strengths(Cpu::new(&program)).iter().sum();
         assert_eq!(13_140, sum);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_second_half() {
+        let program = parse(include_str!("test_input.txt"));
+        let pixels = get_display_pixels(Cpu::new(&program));
+        insta::assert_snapshot!("test_input_display", render_display(&pixels));
+        // The example draws stripes, not letters
+        assert!(ocr::decode(&render_display(&pixels)).is_err());
+    }
+
+    #[test]
+    fn test_small_program() {
+        let program = parse("noop\naddx 3\naddx -5");
+        let x: Vec<i32> = Cpu::new(&program).map(|state| state.registers.get('x')).collect();
+        assert_eq!(vec![1, 1, 1, 4, 4], x);
+
+        let mut cpu = Cpu::new(&program);
+        cpu.by_ref().for_each(drop);
+        assert_eq!(-1, cpu.registers.get('x'));
+        assert_eq!(6, cpu.cycle);
+    }
+
+    #[test]
+    fn test_multiple_registers_and_costs() {
+        let mut set = InstructionSet::standard();
+        set.define("addy", 3, InstructionSet::standard().specs["addy"].parse);
+        set.define("mov", 1, |_, args| match args {
+            [from, to] => {
+                let (Some(from), Some(to)) = (from.chars().next(), to.chars().next()) else {
+                    return Err(anyhow!("mov takes two registers"));
+                };
+                Ok(Box::new(Mov { from, to }))
+            },
+            _ => Err(anyhow!("mov takes two registers")),
+        });
+
+        let program = set.parse_program("addy 7\nmov y x\naddx 1").unwrap();
+        let states: Vec<CycleState> = Cpu::new(&program).collect();
+
+        assert_eq!(6, states.len());
+        assert_eq!(vec![0, 0, 0, 7, 7, 7], states.iter().map(|state| state.registers.get('y')).collect::<Vec<i32>>());
+        assert_eq!(vec![1, 1, 1, 1, 7, 7], states.iter().map(|state| state.registers.get('x')).collect::<Vec<i32>>());
+
+        assert!(set.parse_program("addy 7\nfoo").is_err());
+        assert!(set.parse_program("addx").is_err());
+    }
+
+    #[derive(Debug)]
+    struct Mov {
+        from: char,
+        to: char,
+    }
+
+    impl Instruction for Mov {
+        fn execute(&self, registers: &mut Registers) {
+            registers.set(self.to, registers.get(self.from));
+        }
+    }
+
+    #[test]
+    fn test_triggers() {
+        let program = parse(include_str!("test_input.txt"));
+        let mut cpu = Cpu::new(&program);
+
+        let (state, trigger) = cpu.run_until(&[Trigger::Cycle(20)]).unwrap();
+        assert_eq!((20, 21), (state.cycle, state.registers.get('x')));
+        assert_eq!(Trigger::Cycle(20), trigger);
+
+        let (state, _) = cpu.run_until(&[Trigger::RegisterChanges('x')]).unwrap();
+        assert_eq!(22, state.cycle);
+        assert_eq!(20, state.registers.get('x'));
+
+        let (state, _) = cpu.run_until(&[Trigger::Line(30), Trigger::RegisterEquals('x', -100)]).unwrap();
+        assert_eq!(30, state.line);
+
+        assert_eq!(None, cpu.run_until(&[Trigger::RegisterEquals('x', -100)]));
+
+        assert_eq!(Trigger::Line(2), "line:3".parse().unwrap());
+        assert_eq!(Trigger::Cycle(20), "cycle:20".parse().unwrap());
+        assert_eq!(Trigger::RegisterEquals('x', -5), "x=-5".parse().unwrap());
+        assert_eq!(Trigger::RegisterChanges('y'), "y".parse().unwrap());
+        assert!("line:0".parse::<Trigger>().is_err());
+        assert!("xy".parse::<Trigger>().is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Result, anyhow};
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+
+// 4x6 letters drawn by these puzzles, rows joined from top to bottom
+const FONT: [(char, &str); 18] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Y', "#...#....#.#..#...#...#."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+/// Reads the letters of a rendered CRT, with `#` for lit pixels and `.` for dark ones.
+/// Letters are 4 pixels wide, with a column of space after each of them.
+pub fn decode(display: &str) -> Result<String> {
+    let rows: Vec<&str> = display.lines().filter(|line| !line.is_empty()).collect();
+    if let Some(c) = rows.iter().flat_map(|row| row.chars()).find(|c| !c.is_ascii()) {
+        return Err(anyhow!("Pixels should be ASCII characters such as # and ., got {c}"));
+    }
+    if rows.len() != GLYPH_HEIGHT {
+        return Err(anyhow!("Expected {GLYPH_HEIGHT} rows of pixels, got {}", rows.len()));
+    }
+    let width = rows[0].len();
+    if rows.iter().any(|row| row.len() != width) {
+        return Err(anyhow!("Rows of pixels should all have the same length"));
+    }
+
+    let mut letters = String::new();
+    let mut unknown = vec![];
+
+    for (index, start) in (0..width).step_by(GLYPH_WIDTH + 1).enumerate() {
+        let end = (start + GLYPH_WIDTH).min(width);
+        let glyph: String = rows.iter().map(|row| format!("{:.<GLYPH_WIDTH$}", &row[start..end])).collect();
+
+        match FONT.iter().find(|(_, pixels)| *pixels == glyph) {
+            Some((letter, _)) => letters.push(*letter),
+            None => unknown.push(format!("glyph {} at column {start}:\n{}", index + 1, draw(&glyph))),
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(letters)
+    } else {
+        Err(anyhow!("Unknown glyphs in {letters:?}, where they are left out:\n{}", unknown.join("\n")))
+    }
+}
+
+fn draw(glyph: &str) -> String {
+    glyph
+        .as_bytes()
+        .chunks(GLYPH_WIDTH)
+        .map(|row| String::from_utf8_lossy(row).into_owned())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(letters: &str) -> String {
+        (0..GLYPH_HEIGHT)
+            .map(|row| {
+                letters
+                    .chars()
+                    .map(|letter| {
+                        let (_, pixels) = FONT.iter().find(|(font_letter, _)| *font_letter == letter).unwrap();
+                        format!("{}.", &pixels[row * GLYPH_WIDTH..(row + 1) * GLYPH_WIDTH])
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_decode() {
+        let display = "\
+####.#....###..#....####..##..####.#....
+#....#....#..#.#.......#.#..#....#.#....
+###..#....#..#.#......#..#......#..#....
+#....#....###..#.....#...#.##..#...#....
+#....#....#....#....#....#..#.#....#....
+####.####.#....####.####..###.####.####.";
+
+        assert_eq!("ELPLZGZL", decode(display).unwrap());
+    }
+
+    #[test]
+    fn test_whole_font() {
+        let letters: String = FONT.iter().map(|(letter, _)| letter).collect();
+        assert_eq!(letters, decode(&render(&letters)).unwrap());
+
+        // Without the space after the last letter
+        let display = render("HI");
+        let rows: Vec<&str> = display.lines().map(|row| &row[..9]).collect();
+        assert_eq!("HI", decode(&rows.join("\n")).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let display = render("AB").replacen('#', ".", 1);
+        let err = decode(&display).unwrap_err().to_string();
+        assert!(err.contains("\"B\""), "{err}");
+        assert!(err.contains("glyph 1 at column 0:\n..#.\n#..#"), "{err}");
+
+        assert!(decode("#..#\n#..#").is_err());
+        assert_eq!("Pixels should be ASCII characters such as # and ., got █", decode(&render("AB").replacen('#', "█", 1)).unwrap_err().to_string());
+        assert!(decode(&render("AB").replacen('.', "", 1)).is_err());
+    }
+}
@@ -1,11 +1,16 @@
 use std::{str::FromStr, cmp::Ordering};
 
 use anyhow::{Result, anyhow, Error, Ok};
+use num_bigint::BigUint;
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 enum Op {
     Add,
-    Mult
+    Sub,
+    Mult,
+    Div,
+    Rem,
+    Pow,
 }
 
 impl FromStr for Op {
@@ -13,35 +18,287 @@ impl FromStr for Op {
 
     fn from_str(s: &str) -> Result<Self> {
         match s.trim() {
-            "*" => Ok(Op::Mult),
             "+" => Ok(Op::Add),
+            "-" => Ok(Op::Sub),
+            "*" => Ok(Op::Mult),
+            "/" => Ok(Op::Div),
+            "%" => Ok(Op::Rem),
+            "^" => Ok(Op::Pow),
             _ => Err(anyhow!("Could not parse op."))
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
-enum Operand {
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mult => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+            Op::Pow => "^",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+impl Op {
+    fn precedence(self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1,
+            Op::Mult | Op::Div | Op::Rem => 2,
+            Op::Pow => 3,
+        }
+    }
+
+    fn checked<W: Worry>(self, lhs: &W, rhs: &W) -> Result<W> {
+        W::checked(self, lhs, rhs).ok_or_else(|| {
+            let problem = match self {
+                Op::Sub => "goes below zero",
+                Op::Div | Op::Rem => "divides by zero",
+                Op::Add | Op::Mult | Op::Pow => "overflows",
+            };
+            anyhow!("{lhs} {self} {rhs} {problem}")
+        })
+    }
+}
+
+/// Worry level of an item, with arithmetic failing instead of overflowing.
+trait Worry: Clone + Ord + FromStr + From<u64> + std::fmt::Display
+where
+    for<'a> Self: std::iter::Sum<&'a Self>,
+{
+    /// Whether worry levels are kept modulo the tests' divisors.
+    /// Big integers go without, to check the reduction doesn't change where items go.
+    const REDUCED: bool;
+
+    /// None when the result doesn't fit, would be negative or divides by zero.
+    fn checked(op: Op, lhs: &Self, rhs: &Self) -> Option<Self>;
+
+    fn to_u64(&self) -> Option<u64>;
+}
+
+impl Worry for u64 {
+    const REDUCED: bool = true;
+
+    fn checked(op: Op, lhs: &u64, rhs: &u64) -> Option<u64> {
+        match op {
+            Op::Add => lhs.checked_add(*rhs),
+            Op::Sub => lhs.checked_sub(*rhs),
+            Op::Mult => lhs.checked_mul(*rhs),
+            Op::Div => lhs.checked_div(*rhs),
+            Op::Rem => lhs.checked_rem(*rhs),
+            Op::Pow => u32::try_from(*rhs).ok().and_then(|exponent| lhs.checked_pow(exponent)),
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        Some(*self)
+    }
+}
+
+impl Worry for BigUint {
+    const REDUCED: bool = false;
+
+    fn checked(op: Op, lhs: &BigUint, rhs: &BigUint) -> Option<BigUint> {
+        match op {
+            Op::Add => Some(lhs + rhs),
+            Op::Sub => (lhs >= rhs).then(|| lhs - rhs),
+            Op::Mult => Some(lhs * rhs),
+            Op::Div => (*rhs != BigUint::ZERO).then(|| lhs / rhs),
+            Op::Rem => (*rhs != BigUint::ZERO).then(|| lhs % rhs),
+            Op::Pow => u32::try_from(rhs).ok().map(|exponent| lhs.pow(exponent)),
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        u64::try_from(self).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
     Num(u64),
-    Old
+    Old,
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' => { chars.next(); },
+            '(' => { chars.next(); tokens.push(Token::Open); },
+            ')' => { chars.next(); tokens.push(Token::Close); },
+            '0'..='9' => {
+                let mut num = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    num.push(digit);
+                }
+                tokens.push(Token::Num(num.parse()?));
+            },
+            'a'..='z' => {
+                let mut word = String::new();
+                while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+                    word.push(letter);
+                }
+                if word != "old" {
+                    return Err(anyhow!("Unknown name {word} in expression, only old can be used"));
+                }
+                tokens.push(Token::Old);
+            },
+            _ => {
+                chars.next();
+                tokens.push(Token::Op(c.to_string().parse().map_err(|_| anyhow!("Unexpected {c} in expression"))?));
+            },
+        }
+    }
+
+    Ok(tokens)
 }
 
-impl FromStr for Operand {
+/// Arithmetic on the old worry level of an item.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum Expr {
+    Num(u64),
+    Old,
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+impl FromStr for Expr {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        match s.trim() {
-            "old" => Ok(Operand::Old),
-            num => Ok(Operand::Num(num.parse::<u64>()?))
+        let mut tokens = tokenize(s)?.into_iter().peekable();
+        let expr = Expr::parse(&mut tokens, 0)?;
+
+        match tokens.next() {
+            None => Ok(expr),
+            Some(token) => Err(anyhow!("Unexpected {token:?} in expression {s}")),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Num(num) => write!(f, "{num}"),
+            Expr::Old => write!(f, "old"),
+            Expr::Binary(op, lhs, rhs) => {
+                // Nested operations get parentheses so the precedence doesn't matter
+                let operand = |expr: &Expr| match expr {
+                    Expr::Binary(..) => format!("({expr})"),
+                    _ => expr.to_string(),
+                };
+                write!(f, "{} {op} {}", operand(lhs), operand(rhs))
+            },
+        }
+    }
+}
+
+impl Expr {
+    /// Precedence climbing, only taking operators binding at least as tightly as min_precedence.
+    fn parse(tokens: &mut std::iter::Peekable<std::vec::IntoIter<Token>>, min_precedence: u8) -> Result<Expr> {
+        let mut lhs = match tokens.next() {
+            Some(Token::Num(num)) => Expr::Num(num),
+            Some(Token::Old) => Expr::Old,
+            Some(Token::Open) => {
+                let expr = Expr::parse(tokens, 0)?;
+                match tokens.next() {
+                    Some(Token::Close) => expr,
+                    _ => return Err(anyhow!("Missing closing parenthesis")),
+                }
+            },
+            token => return Err(anyhow!("Expected a number, old or an opening parenthesis, got {token:?}")),
+        };
+
+        while let Some(&Token::Op(op)) = tokens.peek() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            tokens.next();
+            // Powers group from the right, the other operations from the left
+            let next_precedence = if op == Op::Pow { op.precedence() } else { op.precedence() + 1 };
+            let rhs = Expr::parse(tokens, next_precedence)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn uses_old(&self) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Old => true,
+            Expr::Binary(_, lhs, rhs) => lhs.uses_old() || rhs.uses_old(),
+        }
+    }
+
+    /// Whether the result modulo any number only depends on old modulo that number.
+    /// Subtractions aren't, as exact worry levels can't go below zero where remainders just wrap around.
+    fn is_modular(&self) -> bool {
+        match self {
+            Expr::Num(_) | Expr::Old => true,
+            Expr::Binary(Op::Add | Op::Mult, lhs, rhs) => lhs.is_modular() && rhs.is_modular(),
+            Expr::Binary(Op::Pow, base, exponent) => base.is_modular() && !exponent.uses_old(),
+            Expr::Binary(Op::Sub | Op::Div | Op::Rem, _, _) => false,
+        }
+    }
+
+    fn eval<W: Worry>(&self, old: &W) -> Result<W> {
+        match self {
+            Expr::Num(num) => Ok(W::from(*num)),
+            Expr::Old => Ok(old.clone()),
+            Expr::Binary(op, lhs, rhs) => op.checked(&lhs.eval(old)?, &rhs.eval(old)?),
+        }
+    }
+
+    /// Evaluates modulo modulus, which only makes sense for modular expressions.
+    fn eval_mod(&self, old: u64, modulus: u64) -> Result<u64> {
+        // Operands are below the modulus, so a u128 holds their sum or product
+        let operands = |lhs: &Expr, rhs: &Expr| -> Result<(u128, u128)> {
+            Ok((lhs.eval_mod(old, modulus)? as u128, rhs.eval_mod(old, modulus)? as u128))
+        };
+        let wide_modulus = modulus as u128;
+
+        let value = match self {
+            Expr::Num(num) => *num as u128,
+            Expr::Old => old as u128,
+            Expr::Binary(Op::Add, lhs, rhs) => {
+                let (lhs, rhs) = operands(lhs, rhs)?;
+                lhs + rhs
+            },
+            Expr::Binary(Op::Mult, lhs, rhs) => {
+                let (lhs, rhs) = operands(lhs, rhs)?;
+                lhs * rhs
+            },
+            Expr::Binary(Op::Pow, base, exponent) => {
+                let (mut base, mut exponent) = (base.eval_mod(old, modulus)? as u128, exponent.eval(&old)?);
+                let mut value = 1;
+                while exponent > 0 {
+                    if exponent & 1 == 1 {
+                        value = value * base % wide_modulus;
+                    }
+                    base = base * base % wide_modulus;
+                    exponent >>= 1;
+                }
+                value
+            },
+            Expr::Binary(Op::Sub | Op::Div | Op::Rem, _, _) => self.eval(&old)? as u128,
+        };
+
+        Ok((value % wide_modulus) as u64)
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 struct MonkeyOp {
-    operand1: Operand, 
-    op: Op, 
-    operand2: Operand
+    expr: Expr,
 }
 
 impl FromStr for MonkeyOp {
@@ -49,42 +306,105 @@ impl FromStr for MonkeyOp {
 
     fn from_str(s: &str) -> Result<Self> {
         let s = s.split(" = ").last().ok_or(anyhow!("Could not split line by '=' to parse MonkeyOp"))?;
-        let os: Vec<_> = s.split_ascii_whitespace().take(3).collect();
+        let expr = s.parse::<Expr>().map_err(|err| anyhow!("Couldn't parse Monkey operation: {err}"))?;
 
-        if os.len() < 3 {
-            return Err(anyhow!("Couldn't parse Monkey operation"));
-        }
-        
-        let oper1 = os[0].parse::<Operand>()?;
-        let op = os[1].parse::<Op>()?;
-        let oper2 = os[2].parse::<Operand>()?;
-    
-        Ok(MonkeyOp{operand1: oper1, op, operand2: oper2})
+        Ok(MonkeyOp { expr })
     }
 }
 
 impl MonkeyOp {
-    fn apply_op(&self, old_val: u64) -> u64 {
-        let op1 = match self.operand1 {
-            Operand::Old => old_val,
-            Operand::Num(num) => num,
-        };
+    fn apply_op<W: Worry>(&self, old_val: &W) -> Result<W> {
+        self.expr.eval(old_val)
+    }
 
-        let op2 = match self.operand2 {
-            Operand::Old => old_val,
-            Operand::Num(num) => num,
-        };
+    fn apply_op_mod(&self, old_val: u64, modulus: u64) -> Result<u64> {
+        self.expr.eval_mod(old_val, modulus)
+    }
+}
+
+/// Condition on the new worry level, like `divisible by 3 and not greater than 100`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum Condition {
+    DivisibleBy(u64),
+    GreaterThan(u64),
+    LessThan(u64),
+    EqualTo(u64),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
 
-        match self.op {
-            Op::Add => op1 + op2,
-            Op::Mult => op1 * op2,
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        // `or` binds more loosely than `and`, which binds more loosely than `not`
+        if let Some((lhs, rhs)) = s.split_once(" or ") {
+            return Ok(Condition::Or(Box::new(lhs.parse()?), Box::new(rhs.parse()?)));
+        }
+        if let Some((lhs, rhs)) = s.split_once(" and ") {
+            return Ok(Condition::And(Box::new(lhs.parse()?), Box::new(rhs.parse()?)));
+        }
+        if let Some(condition) = s.strip_prefix("not ") {
+            return Ok(Condition::Not(Box::new(condition.parse()?)));
+        }
+
+        let (predicate, num) = s.rsplit_once(' ').ok_or(anyhow!("Could not parse test condition {s}"))?;
+        let num = num.parse::<u64>().map_err(|_| anyhow!("Could not parse number in test condition {s}"))?;
+        match predicate {
+            "divisible by" if num == 0 => Err(anyhow!("Nothing is divisible by 0")),
+            "divisible by" => Ok(Condition::DivisibleBy(num)),
+            "greater than" => Ok(Condition::GreaterThan(num)),
+            "less than" => Ok(Condition::LessThan(num)),
+            "equal to" => Ok(Condition::EqualTo(num)),
+            _ => Err(anyhow!("Unknown test condition {s}")),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::DivisibleBy(num) => write!(f, "divisible by {num}"),
+            Condition::GreaterThan(num) => write!(f, "greater than {num}"),
+            Condition::LessThan(num) => write!(f, "less than {num}"),
+            Condition::EqualTo(num) => write!(f, "equal to {num}"),
+            Condition::Not(condition) => write!(f, "not {condition}"),
+            Condition::And(lhs, rhs) => write!(f, "{lhs} and {rhs}"),
+            Condition::Or(lhs, rhs) => write!(f, "{lhs} or {rhs}"),
+        }
+    }
+}
+
+impl Condition {
+    fn holds<W: Worry>(&self, val: &W) -> bool {
+        match self {
+            Condition::DivisibleBy(num) => W::checked(Op::Rem, val, &W::from(*num)) == Some(W::from(0)),
+            Condition::GreaterThan(num) => *val > W::from(*num),
+            Condition::LessThan(num) => *val < W::from(*num),
+            Condition::EqualTo(num) => *val == W::from(*num),
+            Condition::Not(condition) => !condition.holds(val),
+            Condition::And(lhs, rhs) => lhs.holds(val) && rhs.holds(val),
+            Condition::Or(lhs, rhs) => lhs.holds(val) || rhs.holds(val),
+        }
+    }
+
+    /// Divisors checked by the condition, if divisibility is all it checks.
+    fn divisors(&self) -> Option<Vec<u64>> {
+        match self {
+            Condition::DivisibleBy(num) => Some(vec![*num]),
+            Condition::GreaterThan(_) | Condition::LessThan(_) | Condition::EqualTo(_) => None,
+            Condition::Not(condition) => condition.divisors(),
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => Some([lhs.divisors()?, rhs.divisors()?].concat()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 struct Test {
-    div: u64,
+    condition: Condition,
     truthy: usize,
     falsy: usize
 }
@@ -93,30 +413,32 @@ impl FromStr for Test {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        let nums = s
-            .lines()
-            // .map(|line| line.split_ascii_whitespace())
-            .filter_map(|line|
-                line
-                    .split_ascii_whitespace()
-                    .filter_map(|word| word.parse::<u64>().ok())
-                    .fold(None, |_acc, x| Some(x))
-            )
-            .collect::<Vec<u64>>();
-
-        let mut nums_iter = nums.iter();
-
-        let div = nums_iter.next().ok_or(anyhow!("Could not parse Test"))?.clone() as u64;
-        let truthy = nums_iter.next().ok_or(anyhow!("Could not parse Test"))?.clone() as usize;
-        let falsy = nums_iter.next().ok_or(anyhow!("Could not parse Test"))?.clone() as usize;
+        let mut lines = s.lines();
 
-        Ok(Test{div, truthy, falsy})
+        let condition = lines
+            .next()
+            .and_then(|line| line.split_once("Test:"))
+            .ok_or(anyhow!("Could not parse Test"))?
+            .1
+            .parse::<Condition>()?;
+
+        let mut targets = lines.map(|line|
+            line
+                .split_ascii_whitespace()
+                .last()
+                .and_then(|word| word.parse::<usize>().ok())
+        );
+
+        let truthy = targets.next().flatten().ok_or(anyhow!("Could not parse Test"))?;
+        let falsy = targets.next().flatten().ok_or(anyhow!("Could not parse Test"))?;
+
+        Ok(Test{condition, truthy, falsy})
     }
 }
 
 impl Test {
-    fn test(self, val: u64) -> usize {
-        if val % self.div == 0 {
+    fn test<W: Worry>(&self, val: &W) -> usize {
+        if self.condition.holds(val) {
             self.truthy
         } 
         else {
@@ -126,15 +448,15 @@ impl Test {
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-struct Monkey {
+struct Monkey<W = u64> {
     index: usize,
-    items: Vec<u64>,
+    items: Vec<W>,
     operation: MonkeyOp,
     test: Test,
     inspect_count: u64,
 }
 
-impl FromStr for Monkey {
+impl<W: Worry> FromStr for Monkey<W> {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
@@ -156,11 +478,11 @@ impl FromStr for Monkey {
             .captures_iter(lines.next().ok_or(anyhow!("String to parse as Monkey did not include enough lines."))?)
             .filter_map(|captures| 
                 captures.get(0).map(|match_| 
-                    match_.as_str().parse::<u64>()
+                    match_.as_str().parse::<W>()
                     .ok()
                 )
             )
-            .collect::<Option<Vec<u64>>>()
+            .collect::<Option<Vec<W>>>()
             .ok_or(anyhow!("Failed to parse items"))?;
 
         let operation = lines
@@ -178,18 +500,23 @@ impl FromStr for Monkey {
     }
 }
 
-impl std::fmt::Display for Monkey {
+impl<W: Worry> std::fmt::Display for Monkey<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Monkey {}: ", self.index)?;
-        let items_str: Vec<String> = self.items.iter().map(|&item| item.to_string()).collect();
+        let items_str: Vec<String> = self.items.iter().map(|item| item.to_string()).collect();
         write!(f, "{}", items_str.join(", "))
     }
 }
 
-fn parse_input_to_monkeys(string: &str) -> Vec<Monkey> {
+fn parse_input_to_monkeys<W: Worry>(string: &str) -> Result<Vec<Monkey<W>>> {
     string
         .split("\n\n")
-        .filter_map(|monkey_str| monkey_str.parse::<Monkey>().ok())
+        .enumerate()
+        .map(|(position, monkey_str)| {
+            monkey_str
+                .parse::<Monkey<W>>()
+                .map_err(|err| anyhow!("Could not parse the notes of monkey {position}: {err}"))
+        })
         .collect()
 }
 
@@ -201,23 +528,80 @@ fn gcd(a: u64, b: u64) -> u64 {
     }
 }
 
-fn lcm(a: u64, b: u64) -> u64 {
-    a * b / gcd(a, b)
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    (a / gcd(a, b)).checked_mul(b)
 }
 
-fn simulate_round(monkeys: &mut Vec<Monkey>, div_worry_by: u64) {
+/// Modulus worry levels can be kept under without changing where the items go,
+/// or why they can't be reduced.
+fn worry_modulus<W: Worry>(monkeys: &[Monkey<W>]) -> Result<u64> {
+    let mut modulus = Some(1);
+    let mut reasons = vec![];
+
+    for monkey in monkeys {
+        if !monkey.operation.expr.is_modular() {
+            reasons.push(format!("the operation of monkey {} ({}) does not keep remainders", monkey.index, monkey.operation.expr));
+        }
+        match monkey.test.condition.divisors() {
+            Some(divisors) => modulus = modulus.and_then(|modulus| divisors.into_iter().try_fold(modulus, lcm)),
+            None => reasons.push(format!("the test of monkey {} ({}) does not only check divisibility", monkey.index, monkey.test.condition)),
+        }
+    }
+
+    if modulus.is_none() {
+        reasons.push("the least common multiple of the divisors overflows".to_string());
+    }
+
+    match modulus {
+        Some(modulus) if reasons.is_empty() => Ok(modulus),
+        _ => Err(anyhow!("Worry levels can't be reduced, {}", reasons.join(", "))),
+    }
+}
+
+/// How worry levels are kept in check after each inspection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reduction {
+    /// Divided by the relief, a relief of 1 keeping them exact
+    Relief(u64),
+    /// Kept modulo the tests' divisors, which dividing by a relief wouldn't preserve
+    Modulus(u64),
+}
+
+impl Reduction {
+    /// Worry levels are kept modulo the tests' divisors unless there is some relief or they are exact.
+    /// Fails with the reason when they should be but can't, rather than risking an overflow later on.
+    fn new<W: Worry>(monkeys: &[Monkey<W>], div_worry_by: u64) -> Result<Self> {
+        if div_worry_by != 1 || !W::REDUCED {
+            return Ok(Reduction::Relief(div_worry_by));
+        }
+        worry_modulus(monkeys).map(Reduction::Modulus)
+    }
+}
+
+/// Fails when a worry level can't be computed, naming the monkey and the round (starting at 1).
+fn simulate_round<W: Worry>(monkeys: &mut [Monkey<W>], reduction: Reduction, round: usize) -> Result<()> {
     let len = monkeys.len();
-    let common_factor = monkeys.iter().map(|m| m.test.div).fold(1, |acc, x| lcm(acc, x));
 
     for ind in 0..len {
-        // Assumes a monkey can't pass to himself
         for item_ind in 0..monkeys[ind].items.len() {
-            let new_val = monkeys[ind].operation.apply_op(monkeys[ind].items[item_ind]) / div_worry_by;
-            let new_val = new_val % common_factor;
-            let new_ind = monkeys[ind].test.test(new_val);
+            let old_val = &monkeys[ind].items[item_ind];
+            let new_val = match (reduction, old_val.to_u64()) {
+                (Reduction::Modulus(modulus), Some(old_val)) => monkeys[ind].operation.apply_op_mod(old_val, modulus).map(W::from),
+                (Reduction::Modulus(_), None) => monkeys[ind].operation.apply_op(old_val),
+                (Reduction::Relief(relief), _) => monkeys[ind]
+                    .operation
+                    .apply_op(old_val)
+                    .and_then(|new_val| Op::Div.checked(&new_val, &W::from(relief))),
+            }
+            .map_err(|err| anyhow!("Monkey {} in round {round}: {err}", monkeys[ind].index))?;
+            let new_ind = monkeys[ind].test.test(&new_val);
 
-            assert_ne!(new_ind, monkeys[ind].index);
-            assert!(new_ind < len);
+            if new_ind == ind {
+                return Err(anyhow!("Monkey {} in round {round} throws to itself", monkeys[ind].index));
+            }
+            if new_ind >= len {
+                return Err(anyhow!("Monkey {} in round {round} throws to monkey {new_ind}, out of {len}", monkeys[ind].index));
+            }
 
             monkeys[new_ind].items.push(new_val);
             monkeys[ind].inspect_count += 1;
@@ -225,17 +609,19 @@ fn simulate_round(monkeys: &mut Vec<Monkey>, div_worry_by: u64) {
 
         monkeys[ind].items.clear();
     }
+
+    Ok(())
 }
 
-fn calc_score(monkeys: &Vec<Monkey>) -> u64 {
-    let mut monkeys = monkeys.clone();
+fn calc_score<W: Worry>(monkeys: &[Monkey<W>]) -> u64 {
+    let mut monkeys = monkeys.to_vec();
 
     monkeys.sort_by(|a, b| {
         let cm = b.inspect_count.cmp(&a.inspect_count);
 
         if cm == Ordering::Equal {
-            let asum: u64 = a.items.iter().sum();
-            let bsum: u64 = b.items.iter().sum();
+            let asum: W = a.items.iter().sum();
+            let bsum: W = b.items.iter().sum();
             return bsum.cmp(&asum);
         }
 
@@ -247,18 +633,57 @@ fn calc_score(monkeys: &Vec<Monkey>) -> u64 {
         .fold(1, |acc, x| acc * x.inspect_count)
 }
 
-fn main() {
-    let input = include_str!("input.txt");
-    let mut monkeys = parse_input_to_monkeys(input);
+/// Parses the notes and simulates rounds, starting from the first one.
+/// Notes whose worry levels can't be reduced need some relief or exact worry levels.
+fn simulate_rounds<W: Worry>(notes: &str, rounds: usize, div_worry_by: u64) -> Result<Vec<Monkey<W>>> {
+    let mut monkeys = parse_input_to_monkeys(notes)?;
+    let reduction = Reduction::new(&monkeys, div_worry_by)?;
 
-    for _ in 0..10 {
-        for _ in 0..1000 {
-            simulate_round(&mut monkeys, 1);
-        }
+    for round in 1..=rounds {
+        simulate_round(&mut monkeys, reduction, round)?;
     }
 
+    Ok(monkeys)
+}
+
+fn main() -> Result<()> {
+    let input = include_str!("input.txt");
+    let monkeys: Vec<Monkey> = simulate_rounds(input, 10_000, 1)?;
     let score = calc_score(&monkeys);
     println!("{}", score);
+
+    // Compares the reduced worry levels with exact ones, which only stay small enough for a few rounds
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("exact") {
+        let notes = match args.get(1) {
+            Some(path) => std::fs::read_to_string(path).map_err(|err| anyhow!("Could not read {path}: {err}"))?,
+            None => include_str!("test_input.txt").to_string(),
+        };
+        let number = |index: usize, default: u64| args.get(index).map_or(default, |arg| arg.parse().unwrap_or_else(|_| panic!("Invalid number {arg}")));
+        let (rounds, relief) = (number(2, 20) as usize, number(3, 1));
+
+        let exact: Vec<Monkey<BigUint>> = simulate_rounds(&notes, rounds, relief)?;
+        let reduced: Vec<Monkey> = match simulate_rounds(&notes, rounds, relief) {
+            std::result::Result::Ok(reduced) => reduced,
+            Err(err) => {
+                let largest = exact.iter().flat_map(|monkey| &monkey.items).map(BigUint::bits).max().unwrap_or(0);
+                println!("Reduced: {err}\nExact score: {}, largest worry level of {largest} bits", calc_score(&exact));
+                return Ok(());
+            },
+        };
+
+        for (reduced, exact) in reduced.iter().zip(&exact) {
+            let largest = exact.items.iter().map(BigUint::bits).max().unwrap_or(0);
+            println!(
+                "Monkey {}: {} inspections reduced, {} exact, largest worry level of {largest} bits",
+                reduced.index, reduced.inspect_count, exact.inspect_count
+            );
+        }
+        let matches = reduced.iter().zip(&exact).all(|(reduced, exact)| reduced.inspect_count == exact.inspect_count);
+        println!("Scores: {} reduced, {} exact, {}", calc_score(&reduced), calc_score(&exact), if matches { "matching" } else { "NOT matching" });
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -268,10 +693,10 @@ mod tests {
     #[test]
     fn test_first_half() {
         let input = include_str!("test_input.txt");
-        let mut monkeys = parse_input_to_monkeys(input);
+        let mut monkeys: Vec<Monkey> = parse_input_to_monkeys(input).unwrap();
 
-        for _ in 0..20 {
-            simulate_round(&mut monkeys, 3);
+        for round in 1..=20 {
+            simulate_round(&mut monkeys, Reduction::Relief(3), round).unwrap();
         }
     
         let res = calc_score(&monkeys);
@@ -281,21 +706,185 @@ mod tests {
     #[test]
     fn test_second_half() {
         let input = include_str!("test_input.txt");
-        let mut monkeys = parse_input_to_monkeys(input);
+        let mut monkeys: Vec<Monkey> = parse_input_to_monkeys(input).unwrap();
+        let reduction = Reduction::Modulus(worry_modulus(&monkeys).unwrap());
     
-        for _ in 0..10 {
-            for _ in 0..1000 {
-                simulate_round(&mut monkeys, 1);
+        for thousand in 0..10 {
+            for round in 1..=1000 {
+                simulate_round(&mut monkeys, reduction, thousand * 1000 + round).unwrap();
             }
 
             for mon in monkeys.iter() {
                 println!("M{}: {:12}", mon.index, mon.inspect_count);
             }
 
-            println!("");
+            println!();
         }
     
         let score = calc_score(&monkeys);
         assert_eq!(2_713_310_158, score);
     }
+
+    #[test]
+    fn test_expressions() {
+        let expr = "2 + old * (old - 1) ^ 2 ^ 1 % 7".parse::<Expr>().unwrap();
+        assert_eq!("2 + ((old * ((old - 1) ^ (2 ^ 1))) % 7)", expr.to_string());
+        assert_eq!(2 + (5 * 16) % 7, expr.eval(&5_u64).unwrap());
+        assert_eq!(4, "100 / 5 / 5".parse::<Expr>().unwrap().eval(&0_u64).unwrap());
+        assert_eq!(6, "10 - 3 - 1".parse::<Expr>().unwrap().eval(&0_u64).unwrap());
+
+        for invalid in ["", "old +", "(old * 2", "old * 2)", "new * 2", "old & 2", "old old"] {
+            assert!(invalid.parse::<Expr>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_modular_expressions() {
+        let modulus = 7 * 11 * 13;
+
+        for source in ["old * old", "old + 6", "(old + 3) * 5 ^ 3", "old ^ (2 + 1) + old", "2 ^ 10 * old"] {
+            let expr = source.parse::<Expr>().unwrap();
+            assert!(expr.is_modular(), "{source}");
+            for old in [3, 40, 999] {
+                assert_eq!(expr.eval(&old).unwrap() % modulus, expr.eval_mod(old + 5 * modulus, modulus).unwrap(), "{source} {old}");
+            }
+        }
+
+        for source in ["old / 2", "old % 5 + 1", "2 ^ old", "old - 1", "old ^ 3 - old"] {
+            assert!(!source.parse::<Expr>().unwrap().is_modular(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_conditions() {
+        let condition = "divisible by 3 and not greater than 10 or equal to 20".parse::<Condition>().unwrap();
+        assert_eq!("divisible by 3 and not greater than 10 or equal to 20", condition.to_string());
+        let holding: Vec<u64> = (0..25).filter(|val| condition.holds(val)).collect();
+        assert_eq!(vec![0, 3, 6, 9, 20], holding);
+
+        assert_eq!(Some(vec![2, 3]), "divisible by 2 or not divisible by 3".parse::<Condition>().unwrap().divisors());
+        assert_eq!(None, "divisible by 2 and less than 3".parse::<Condition>().unwrap().divisors());
+
+        for invalid in ["divisible by 0", "divisible by", "odd", "bigger than 3", "less than 3 and"] {
+            assert!(invalid.parse::<Condition>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_richer_notes() {
+        let input = "\
+Monkey 0:
+  Starting items: 10, 3
+  Operation: new = (old - 1) * 2
+  Test: greater than 10 or divisible by 4
+    If true: throw to monkey 1
+    If false: throw to monkey 2
+
+Monkey 1:
+  Starting items: 5
+  Operation: new = old ^ 2 / 5
+  Test: not divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 2
+
+Monkey 2:
+  Starting items: 7
+  Operation: new = old % 5 + 1
+  Test: equal to 3 and less than 10
+    If true: throw to monkey 0
+    If false: throw to monkey 1";
+
+        let mut monkeys: Vec<Monkey> = parse_input_to_monkeys(input).unwrap();
+        assert_eq!(3, monkeys.len());
+
+        let err = worry_modulus(&monkeys).unwrap_err().to_string();
+        assert!(err.contains("monkey 0 (greater than 10 or divisible by 4)"), "{err}");
+        assert!(err.contains("monkey 1 ((old ^ 2) / 5)"), "{err}");
+
+        simulate_round(&mut monkeys, Reduction::Relief(1), 1).unwrap();
+        let items: Vec<Vec<u64>> = monkeys.iter().map(|monkey| monkey.items.clone()).collect();
+        assert_eq!(vec![vec![5, 3, 3], vec![5], vec![]], items);
+        assert_eq!(vec![2, 3, 2], monkeys.iter().map(|monkey| monkey.inspect_count).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn test_worry_modulus() {
+        let monkeys: Vec<Monkey> = parse_input_to_monkeys(include_str!("test_input.txt")).unwrap();
+        assert_eq!(23 * 19 * 13 * 17, worry_modulus(&monkeys).unwrap());
+
+        let monkey = "Monkey 0:\n  Starting items: 1\n  Operation: new = old +\n  Test: divisible by 2\n    If true: throw to monkey 1\n    If false: throw to monkey 2";
+        assert!(monkey.parse::<Monkey>().is_err());
+        assert!(monkey.replace("old +", "old + 1").parse::<Monkey>().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_notes() {
+        let input = include_str!("test_input.txt");
+
+        let notes = input.replace("new = old * old", "new = old *");
+        let err = parse_input_to_monkeys::<u64>(&notes).unwrap_err().to_string();
+        assert!(err.starts_with("Could not parse the notes of monkey 2: "), "{err}");
+
+        let notes = input.replace("If false: throw to monkey 3", "If false: throw to monkey 0");
+        let err = simulate_rounds::<u64>(&notes, 1, 3).unwrap_err().to_string();
+        assert_eq!("Monkey 0 in round 1 throws to itself", err);
+
+        let notes = input.replace("If false: throw to monkey 3", "If false: throw to monkey 4");
+        let err = simulate_rounds::<u64>(&notes, 1, 3).unwrap_err().to_string();
+        assert_eq!("Monkey 0 in round 1 throws to monkey 4, out of 4", err);
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let input = include_str!("test_input.txt");
+
+        // Without the reduction, squaring overflows before long
+        let mut monkeys: Vec<Monkey> = parse_input_to_monkeys(input).unwrap();
+        let err = (1..=20).try_for_each(|round| simulate_round(&mut monkeys, Reduction::Relief(1), round)).unwrap_err().to_string();
+        assert_eq!("Monkey 0 in round 13: 13988703546165100909 * 19 overflows", err);
+
+        // So notes that can't be reduced are rejected, unless there is some relief or exact worry levels
+        let notes = input.replace("new = old * old", "new = old * old / 1");
+        let err = simulate_rounds::<u64>(&notes, 20, 1).unwrap_err().to_string();
+        assert_eq!("Worry levels can't be reduced, the operation of monkey 2 ((old * old) / 1) does not keep remainders", err);
+
+        // Subtractions aren't reduced, and go below zero the same way in both modes
+        let notes = input.replace("new = old + 3", "new = old - 80");
+        assert!(simulate_rounds::<u64>(&notes, 20, 1).unwrap_err().to_string().contains("monkey 3 (old - 80)"));
+        let err = simulate_rounds::<BigUint>(&notes, 20, 1).unwrap_err().to_string();
+        assert_eq!("Monkey 3 in round 1: 74 - 80 goes below zero", err);
+        let err = simulate_rounds::<u64>(&notes, 20, 3).unwrap_err().to_string();
+        assert_eq!("Monkey 3 in round 1: 74 - 80 goes below zero", err);
+
+        let err = "old / (old - old)".parse::<Expr>().unwrap().eval(&3_u64).unwrap_err().to_string();
+        assert_eq!("3 / 0 divides by zero", err);
+        assert!("old ^ 64".parse::<Expr>().unwrap().eval(&2_u64).is_err());
+        assert_eq!(None, lcm(u64::MAX, 2));
+    }
+
+    #[test]
+    fn test_exact_worry_levels() {
+        let input = include_str!("test_input.txt");
+
+        let exact: Vec<Monkey<BigUint>> = simulate_rounds(input, 20, 3).unwrap();
+        assert_eq!(10_605, calc_score(&exact));
+
+        // Subtractions staying above zero give the same counts in both modes
+        let notes = input.replace("new = old + 3", "new = old * 2 - 1");
+        let reduced: Vec<Monkey> = simulate_rounds(&notes, 20, 3).unwrap();
+        let exact: Vec<Monkey<BigUint>> = simulate_rounds(&notes, 20, 3).unwrap();
+        assert_eq!(calc_score(&reduced), calc_score(&exact));
+        assert!(reduced.iter().zip(&exact).all(|(reduced, exact)| reduced.inspect_count == exact.inspect_count));
+
+        // Exact worry levels quickly outgrow a u64, yet items go to the same monkeys as with the reduction
+        let reduced: Vec<Monkey> = simulate_rounds(input, 50, 1).unwrap();
+        let exact: Vec<Monkey<BigUint>> = simulate_rounds(input, 50, 1).unwrap();
+        assert!(exact.iter().flat_map(|monkey| &monkey.items).any(|item| item.bits() > 64));
+        let modulus = BigUint::from(worry_modulus(&reduced).unwrap());
+        for (reduced, exact) in reduced.iter().zip(&exact) {
+            assert_eq!(reduced.inspect_count, exact.inspect_count);
+            let exact_items: Vec<BigUint> = exact.items.iter().map(|item| item % &modulus).collect();
+            assert_eq!(reduced.items.iter().map(|&item| BigUint::from(item)).collect::<Vec<BigUint>>(), exact_items);
+        }
+    }
 }
@@ -102,10 +102,11 @@ fn find_lowest_point(point_set: &HashSet<Position>) -> u32 {
     point_set.iter().map(|point| point.y).max().unwrap()
 }
 
-fn print_map(point_set: &HashSet<Position>, floor: Option<u32>) {
+fn render_map(point_set: &HashSet<Position>, floor: Option<u32>) -> String {
     let left_bound = point_set.iter().map(|point| point.x).min().unwrap() - 1;
     let right_bound = point_set.iter().map(|point| point.x).max().unwrap() + 1;
     let _floor;
+    let mut map = String::new();
 
     if let Some(num) = floor {
         _floor = num;
@@ -114,25 +115,29 @@ fn print_map(point_set: &HashSet<Position>, floor: Option<u32>) {
     }
 
     for y in 0..=_floor {
-        print!("{:3}: ", y);
+        map += &format!("{:3}: ", y);
         for x in left_bound..=right_bound {
             let pos = new_pos(x, y);
 
             if y == _floor {
-                print!("-");
+                map.push('-');
             } else if point_set.contains(&pos) {
-                print!("#");
+                map.push('#');
             } else if x == 500 && y == 0 {
-                print!("*");
+                map.push('*');
             } else {
-                print!(".");
+                map.push('.');
             }
         }
 
-        println!();
+        map.push('\n');
     }
 
-    println!();
+    map
+}
+
+fn print_map(point_set: &HashSet<Position>, floor: Option<u32>) {
+    println!("{}", render_map(point_set, floor));
 }
 
 fn calc_sand_grain_count(point_set: &mut HashSet<Position>) -> u32 {
@@ -229,8 +234,11 @@ mod tests {
         let mut positions = parse_positions(input);
 
         print_map(&positions, None);
+        insta::assert_snapshot!("test_input_rocks", render_map(&positions, None));
 
         assert_eq!(24, calc_sand_grain_count(&mut positions));
+
+        insta::assert_snapshot!("test_input_sand_into_abyss", render_map(&positions, None));
     }
 
     #[test]
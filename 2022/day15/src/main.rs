@@ -1,5 +1,6 @@
 use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use std::time::Instant;
 
 #[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
@@ -69,51 +70,54 @@ fn get_minmax_xy(sensors: &Vec<Sensor>) -> Option<(i64, i64, i64, i64)> {
     Some((minX, minY, maxX, maxY))
 }
 
-fn print_map(sensors: &Vec<Sensor>, filled: Option<&HashMap<(i64, i64), i64>>) -> () {
+fn render_map(sensors: &Vec<Sensor>, filled: Option<&HashMap<(i64, i64), i64>>) -> String {
     let (minX, minY, maxX, maxY) = (-10, -12, 30, 27); //get_minmax_xy(sensors).unwrap();
     let mut sensor_set = HashSet::new();
     let mut beacons = HashSet::new();
+    let mut map = String::new();
 
     for sensor in sensors {
         sensor_set.insert((sensor.self_pos.x, sensor.self_pos.y));
         beacons.insert((sensor.beacon.x, sensor.beacon.y));
     }
 
-    print!("    min: {}, {}\n    max: {}, {}\n", minX, minY, maxX, maxY);
+    writeln!(map, "    min: {}, {}\n    max: {}, {}", minX, minY, maxX, maxY).unwrap();
 
-    print!("{:>8}: ", 'x');
+    write!(map, "{:>8}: ", 'x').unwrap();
     for (ind, x) in (minX..=maxX).enumerate() {
         if ind == 0 || (ind as i64) == maxX - minX - 1 {
-            print!("{}", (x % 10).abs());
+            write!(map, "{}", (x % 10).abs()).unwrap();
         } else if ind % 5 == 0 || x % 5 == 0 {
-            print!("{}", (x % 10).abs());
+            write!(map, "{}", (x % 10).abs()).unwrap();
         } else {
-            print!(" ");
+            map.push(' ');
         }
     }
-    print!("\n");
+    map.push('\n');
 
     for y in minY..=maxY {
-        print!("{:>8}: ", y);
+        write!(map, "{:>8}: ", y).unwrap();
 
         for x in minX..=maxX {
             if sensor_set.contains(&(x, y)) {
-                print!("S")
+                map.push('S')
             } else if beacons.contains(&(x, y)) {
-                print!("B")
+                map.push('B')
             } else if let Some(ref occupied) = filled {
                 if occupied.contains_key(&(x, y)) {
-                    print!("#")
+                    map.push('#')
                 } else {
-                    print!(".")
+                    map.push('.')
                 }
             } else {
-                print!(".")
+                map.push('.')
             }
         }
 
-        print!("\n");
+        map.push('\n');
     }
+
+    map
 }
 
 // Too slow for actual solution, and can cause stack overflows. Used to create testcases and paint a filled map based on test input given.
@@ -143,8 +147,8 @@ fn flood_fill(
     }
 }
 
-// Function for creating and printing a filled map
-fn make_print_map(sensors: &Vec<Sensor>) {
+// Fills the area covered by the sensors, returning it along with the count of filled positions of each row
+fn fill_map(sensors: &Vec<Sensor>) -> (HashMap<(i64, i64), i64>, Vec<(i64, u32)>) {
     let (mut minX, mut minY, mut maxX, mut maxY) = get_minmax_xy(sensors).unwrap();
     let mut positions: HashMap<(i64, i64), i64> = HashMap::new();
     // a unknown sensor can't be on a place occupied by a known beacon or sensor
@@ -164,21 +168,36 @@ fn make_print_map(sensors: &Vec<Sensor>) {
         flood_fill(sensor.self_pos.x, sensor.self_pos.y, dist, &mut positions);
     }
 
-    print_map(&sensors, Some(&positions));
+    let row_counts = (minY..=maxY)
+        .map(|y| {
+            let count = (minX..=maxX)
+                .filter(|x| positions.get(&(*x, y)).is_some())
+                .filter(|x| !occupied.contains(&(*x, y)))
+                .count() as u32;
+            (y, count)
+        })
+        .collect();
 
-    for y in minY..=maxY {
-        let count = (minX..=maxX)
-            .into_iter()
-            .filter(|x| positions.get(&(*x, y)).is_some())
-            .filter(|x| !occupied.contains(&(*x, y)))
-            .count() as u32;
+    (positions, row_counts)
+}
 
+// Function for creating a filled map
+fn render_filled_map(sensors: &Vec<Sensor>) -> String {
+    let (positions, _) = fill_map(sensors);
+    render_map(sensors, Some(&positions))
+}
+
+// Function for creating and printing a filled map
+fn make_print_map(sensors: &Vec<Sensor>) {
+    print!("{}", render_filled_map(sensors));
+
+    for (y, count) in fill_map(sensors).1 {
         // print!("{}: {}\n", y, count);
         // Useful for making tests for faster or partial algos
-        print!("assert_eq!({}, calc_solution_1(&sensors, {}));\n", count, y);
+        println!("assert_eq!({}, calc_solution_1(&sensors, {}));", count, y);
     }
 
-    print!("\n");
+    println!();
 }
 
 // Actual solution logic, fast enough
@@ -407,6 +426,26 @@ mod tests {
         assert_eq!(1, calc_solution_1(&sensors, 26));
     }
 
+    #[test]
+    fn test_filled_map() {
+        let input = include_str!("test_input.txt");
+        let sensors = parse_sensors(input);
+
+        insta::assert_snapshot!("test_input_filled_map", render_filled_map(&sensors));
+    }
+
+    #[test]
+    fn test_filled_row_counts() {
+        let input = include_str!("test_input.txt");
+        let sensors = parse_sensors(input);
+        let (_, row_counts) = fill_map(&sensors);
+
+        assert_eq!(37, row_counts.len());
+        for (y, count) in row_counts {
+            assert_eq!(count, calc_solution_1(&sensors, y), "row {y}");
+        }
+    }
+
     #[test]
     fn test_second_half() {
         let input = include_str!("test_input.txt");
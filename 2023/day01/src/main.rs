@@ -1,69 +1,107 @@
-#![feature(portable_simd)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
 use rayon::iter::ParallelIterator;
 use rayon::str::ParallelString;
-use std::simd::{u8x64, Simd, SimdPartialOrd};
 use std::time::Instant;
 
-fn pad_out_and_convert_line(line: &[u8]) -> u8x64 {
-    const LEN: usize = 64;
-    if line.len() == LEN {
-        return u8x64::from_slice(line);
-    }
+// Lines are processed in chunks of this many bytes, so lines of any length are supported
+const LANES: usize = 64;
+
+#[cfg(feature = "simd")]
+mod simd {
+    use super::LANES;
+    use std::simd::cmp::SimdPartialOrd;
+    use std::simd::{u8x64, Select, Simd};
+
+    pub fn pad_out_and_convert_line(line: &[u8]) -> u8x64 {
+        if line.len() == LANES {
+            return u8x64::from_slice(line);
+        }
+
+        assert!(line.len() < LANES, "Chunk had more than {} bytes! Chunk len: {}", LANES, line.len());
 
-    let mut ret: [u8; LEN] = [0; LEN];
-    if line.len() < LEN {
+        let mut ret: [u8; LANES] = [0; LANES];
         ret[..line.len()].copy_from_slice(line);
-        return u8x64::from_slice(&ret);
+        u8x64::from_slice(&ret)
     }
 
-    println!("Line had more than 64 bytes! Line len: {}", line.len());
-    panic!();
+    pub fn convert_char_digits_to_uint(simd_value: u8x64) -> u8x64 {
+        // Allocating these statically does not seem to change performance.
+        // Next step would be to check compiler output with this, and when using lazy static
+        let zero_ascii: Simd<u8, LANES> = u8x64::splat(b'0');
+        let nine: Simd<u8, LANES> = u8x64::splat(9);
+        let null: Simd<u8, LANES> = u8x64::splat(u8::MAX);
+
+        let val = simd_value - zero_ascii;
+        let mask = val.simd_gt(nine);
+
+        mask.select(null, val)
+    }
+
+    pub fn chunk_digits(chunk: &[u8]) -> [u8; LANES] {
+        convert_char_digits_to_uint(pad_out_and_convert_line(chunk)).to_array()
+    }
 }
 
-fn convert_char_digits_to_uint(simd_value: u8x64) -> u8x64 {
-    // Allocating these statically does not seem to change performance.
-    // Next step would be to check compiler output with this, and when using lazy static
-    let ZERO_ASCII: Simd<u8, 64> = u8x64::splat(b'0');
-    let NINE: Simd<u8, 64> = u8x64::splat(9);
-    let NULL: Simd<u8, 64> = u8x64::splat(u8::MAX);
+// Builds on stable, and is used to check the SIMD version when the `simd` feature is enabled
+#[cfg_attr(feature = "simd", allow(dead_code))]
+mod scalar {
+    use super::LANES;
 
-    let val = simd_value - ZERO_ASCII;
-    let mask = val.simd_gt(NINE);
+    pub fn chunk_digits(chunk: &[u8]) -> [u8; LANES] {
+        let mut ret = [u8::MAX; LANES];
 
-    mask.select(NULL, val)
+        for (digit, byte) in ret.iter_mut().zip(chunk) {
+            let val = byte.wrapping_sub(b'0');
+            if val <= 9 {
+                *digit = val;
+            }
+        }
+
+        ret
+    }
 }
 
-fn vec_to_num(simd_val: u8x64) -> u8 {
-    let mut iter = simd_val.as_array().iter().filter(|el| **el < 10);
-    let first = iter.next().unwrap();
-    let last = if let Some(num) = iter.last() {
-        num
-    } else {
-        first
+/// Maps a chunk of at most `LANES` bytes to digit values, any other byte becomes `u8::MAX`.
+type ChunkDigits = fn(&[u8]) -> [u8; LANES];
+
+fn line_to_num(line: &[u8], chunk_digits: ChunkDigits) -> u8 {
+    let mut iter = line
+        .chunks(LANES)
+        .flat_map(chunk_digits)
+        .filter(|el| *el < 10);
+    let Some(first) = iter.next() else {
+        return 0;
     };
+    let last = iter.next_back().unwrap_or(first);
 
-    10 * (*first) + (*last)
+    10 * first + last
 }
 
-fn calc_solution_1(input: &str) -> u32 {
+fn calc_solution_1_with(input: &str, chunk_digits: ChunkDigits) -> u32 {
     if input.len() > 60_000 {
         input
             .par_lines()
-            .map(|line| pad_out_and_convert_line(line.as_bytes()))
-            .map(|bytes| convert_char_digits_to_uint(bytes))
-            .map(|bytes| vec_to_num(bytes) as u32)
+            .map(|line| line_to_num(line.as_bytes(), chunk_digits) as u32)
             .sum()
     } else {
         input
             .lines()
-            .map(|line| pad_out_and_convert_line(line.as_bytes()))
-            .map(|bytes| convert_char_digits_to_uint(bytes))
-            .map(|bytes| vec_to_num(bytes) as u32)
+            .map(|line| line_to_num(line.as_bytes(), chunk_digits) as u32)
             .sum()
     }
 }
 
+#[cfg(feature = "simd")]
+fn calc_solution_1(input: &str) -> u32 {
+    calc_solution_1_with(input, simd::chunk_digits)
+}
+
+#[cfg(not(feature = "simd"))]
+fn calc_solution_1(input: &str) -> u32 {
+    calc_solution_1_with(input, scalar::chunk_digits)
+}
+
 fn digest_line(line: &str) -> u64 {
     let digit_strs = [
         "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "0", "1",
@@ -95,7 +133,7 @@ fn digest_line(line: &str) -> u64 {
 }
 
 fn calc_solution_2(input: &str) -> u64 {
-    input.par_lines().map(|line| digest_line(line)).sum()
+    input.par_lines().map(digest_line).sum()
 }
 
 fn main() {
@@ -137,40 +175,75 @@ fn main() {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "simd")]
     #[test]
     fn test_pad_out_and_convert_line_of_len_64() {
         let line_exact = [b'a'; 64];
-        let result = pad_out_and_convert_line(&line_exact);
-        assert_eq!(result, u8x64::from_slice(&line_exact));
+        let result = simd::pad_out_and_convert_line(&line_exact);
+        assert_eq!(result, std::simd::u8x64::from_slice(&line_exact));
     }
 
+    #[cfg(feature = "simd")]
     #[test]
     fn test_pad_out_and_convert_line_of_len_30() {
         let line_short = [b'b'; 30];
         let mut expected = [b'b'; 64];
         expected[30..].fill(0);
-        let result = pad_out_and_convert_line(&line_short);
-        assert_eq!(result, u8x64::from_slice(&expected));
+        let result = simd::pad_out_and_convert_line(&line_short);
+        assert_eq!(result, std::simd::u8x64::from_slice(&expected));
     }
 
+    #[cfg(feature = "simd")]
     #[test]
     fn test_pad_out_and_convert_line_of_len_65() {
         let line_long = [b'c'; 65];
-        let result = std::panic::catch_unwind(|| pad_out_and_convert_line(&line_long));
+        let result = std::panic::catch_unwind(|| simd::pad_out_and_convert_line(&line_long));
         assert!(result.is_err());
     }
 
+    #[cfg(feature = "simd")]
     #[test]
     fn test_digit_conversion() {
-        let input = ['5' as u8; 64];
-        let expected_output = [5 as u8; 64];
+        let input = [b'5'; 64];
+        let expected_output = [5_u8; 64];
 
-        let raw_output = convert_char_digits_to_uint(u8x64::from_slice(&input));
+        let raw_output = simd::convert_char_digits_to_uint(std::simd::u8x64::from_slice(&input));
         let output = raw_output.as_array();
 
         assert_eq!(expected_output, *output);
     }
 
+    #[cfg(feature = "simd")]
+    #[test]
+    fn test_simd_and_scalar_agree() {
+        let long_line = format!("{}7{}x3{}\n", "a".repeat(100), "b".repeat(64), "c".repeat(63));
+        for input in [include_str!("test_input.txt"), include_str!("input.txt"), long_line.as_str()] {
+            assert_eq!(
+                calc_solution_1_with(input, scalar::chunk_digits),
+                calc_solution_1_with(input, simd::chunk_digits)
+            );
+        }
+
+        for len in 0..200 {
+            let line = (0..len).map(|i| if i % 37 == 5 { b'0' + (i % 10) as u8 } else { b'x' }).collect::<Vec<u8>>();
+            assert_eq!(line_to_num(&line, scalar::chunk_digits), line_to_num(&line, simd::chunk_digits));
+        }
+    }
+
+    #[test]
+    fn test_scalar_digit_conversion() {
+        let mut expected_output = [u8::MAX; LANES];
+        expected_output[..4].copy_from_slice(&[0, 9, u8::MAX, 5]);
+
+        assert_eq!(expected_output, scalar::chunk_digits(b"09a5"));
+    }
+
+    #[test]
+    fn test_lines_longer_than_lanes() {
+        let input = format!("{}4{}2{}\n{}\n", "a".repeat(70), "b".repeat(100), "c".repeat(10), "x1".repeat(LANES));
+        assert_eq!(42 + 11, calc_solution_1(&input));
+    }
+
     #[test]
     fn test_first_half() {
         let input = include_str!("test_input.txt");
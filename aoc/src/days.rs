@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Day {
+    pub year: u32,
+    pub day: u32,
+    pub path: PathBuf,
+}
+
+impl Day {
+    pub fn name(&self) -> String {
+        format!("{}/day{:02}", self.year, self.day)
+    }
+}
+
+/// Directory holding the year folders, the runner lives in a folder next to them.
+pub fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("Runner crate should be inside the repository")
+        .to_path_buf()
+}
+
+/// Finds every `<year>/dayXX` folder containing a crate, sorted by year and day.
+pub fn list_days(root: &Path) -> Result<Vec<Day>> {
+    let mut days = vec![];
+
+    for year_entry in fs::read_dir(root)? {
+        let year_path = year_entry?.path();
+        let Some(year) = year_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u32>().ok())
+        else {
+            continue;
+        };
+
+        for day_entry in fs::read_dir(&year_path)? {
+            let path = day_entry?.path();
+            let day = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("day"))
+                .and_then(|num| num.parse::<u32>().ok());
+
+            if let Some(day) = day {
+                if path.join("Cargo.toml").is_file() {
+                    days.push(Day { year, day, path });
+                }
+            }
+        }
+    }
+
+    days.sort();
+    Ok(days)
+}
+
+pub fn find_day(root: &Path, year: u32, day: u32) -> Result<Day> {
+    list_days(root)?
+        .into_iter()
+        .find(|d| d.year == year && d.day == day)
+        .ok_or(anyhow!("No crate found for {}/day{:02}", year, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_days() {
+        let days = list_days(&repo_root()).unwrap();
+
+        assert!(days.iter().any(|d| d.year == 2022 && d.day == 1));
+        assert!(days.iter().any(|d| d.year == 2023 && d.day == 6));
+        assert!(days.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_find_day() {
+        let day = find_day(&repo_root(), 2022, 10).unwrap();
+
+        assert_eq!("2022/day10", day.name());
+        assert!(day.path.ends_with("2022/day10"));
+        assert!(find_day(&repo_root(), 2022, 42).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+
+mod days;
+mod registry;
+mod report;
+mod runner;
+mod serve;
+mod watch;
+
+#[derive(Parser)]
+#[command(about = "Runner for the Advent of Code solutions in this repository")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rebuild and rerun a day whenever its sources or inputs change
+    Watch {
+        #[arg(long)]
+        year: u32,
+        #[arg(long)]
+        day: u32,
+        /// How often to check for changes, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        poll_ms: u64,
+    },
+    /// Serve the solvers over HTTP on localhost
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Run every day and write an overview of their status, answers and timings
+    Report {
+        #[arg(long, value_enum, default_value_t = Format::Markdown)]
+        format: Format,
+        /// File to write the report to, printed if not given
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Only report on the given year
+        #[arg(long)]
+        year: Option<u32>,
+        /// Seconds after which a slow day is stopped
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Record an answer accepted on the Advent of Code site, which reports then check runs against
+    Verify {
+        #[arg(long)]
+        year: u32,
+        #[arg(long)]
+        day: u32,
+        #[arg(long)]
+        part: u32,
+        answer: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let root = days::repo_root();
+
+    match cli.command {
+        Command::Watch { year, day, poll_ms } => {
+            let day = days::find_day(&root, year, day)?;
+            watch::watch(&day, Duration::from_millis(poll_ms))
+        }
+        Command::Serve { port } => serve::serve(&root, port),
+        Command::Report { format, output, year, timeout } => {
+            let verified = report::load_verified_answers(&report::answers_path())?;
+            let reports = report::build_report(&root, year, &verified, Some(Duration::from_secs(timeout)))?;
+
+            let text = match format {
+                Format::Markdown => report::render_markdown(&reports),
+                Format::Html => report::render_html(&reports),
+            };
+
+            match output {
+                Some(path) => fs::write(path, text)?,
+                None => print!("{}", text),
+            }
+            Ok(())
+        }
+        Command::Verify { year, day, part, answer } => {
+            let day = days::find_day(&root, year, day)?;
+            let answers_path = report::answers_path();
+            let mut verified = report::load_verified_answers(&answers_path)?;
+
+            if let Some(previous) = report::verify_answer(&mut verified, &day, part, &answer)? {
+                eprintln!("Replaced the verified answer {} of {} part {}", previous, day.name(), part);
+            }
+            report::save_verified_answers(&answers_path, &verified)?;
+            Ok(())
+        }
+    }
+}
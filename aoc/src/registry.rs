@@ -0,0 +1,153 @@
+use crate::days::Day;
+
+/// Where a part's answer can be found in a solution's output, lines are counted over non empty lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// The n-th line
+    Line(usize),
+    /// The n-th line which is only a number, for days printing maps or timings around their answers
+    Number(usize),
+    /// The last word of the n-th line
+    LastWord(usize),
+    /// Rest of the first line starting with the label
+    Labelled(&'static str),
+    /// The line after the n-th line equal to the label
+    AfterLabel(&'static str, usize),
+}
+
+impl Answer {
+    pub fn extract(&self, lines: &[String]) -> Option<String> {
+        match *self {
+            Answer::Line(n) => lines.get(n).cloned(),
+            Answer::Number(n) => lines
+                .iter()
+                .filter(|line| line.parse::<i128>().is_ok())
+                .nth(n)
+                .cloned(),
+            Answer::LastWord(n) => lines
+                .get(n)
+                .and_then(|line| line.split_whitespace().last())
+                .map(|word| word.to_string()),
+            Answer::Labelled(label) => lines
+                .iter()
+                .find_map(|line| line.strip_prefix(label))
+                .map(|rest| rest.trim().to_string()),
+            Answer::AfterLabel(label, n) => lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| *line == label)
+                .nth(n)
+                .and_then(|(i, _)| lines.get(i + 1).cloned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solver {
+    pub year: u32,
+    pub day: u32,
+    /// Input file, relative to the day's folder
+    pub input: &'static str,
+    /// None for parts which are not solved
+    pub parts: [Option<Answer>; 2],
+}
+
+impl Solver {
+    pub fn matches(&self, day: &Day) -> bool {
+        self.year == day.year && self.day == day.day
+    }
+
+    pub fn part(&self, part: usize) -> Option<Answer> {
+        part.checked_sub(1).and_then(|i| self.parts.get(i).copied().flatten())
+    }
+}
+
+const fn solver(year: u32, day: u32, parts: [Option<Answer>; 2]) -> Solver {
+    Solver { year, day, input: "src/input.txt", parts }
+}
+
+const TWO_LINES: [Option<Answer>; 2] = [Some(Answer::Line(0)), Some(Answer::Line(1))];
+const SOLUTION_LABELS: [Option<Answer>; 2] = [
+    Some(Answer::AfterLabel("Solution:", 0)),
+    Some(Answer::AfterLabel("Solution:", 1)),
+];
+
+pub const SOLVERS: &[Solver] = &[
+    Solver {
+        input: "input_a.txt",
+        ..solver(2022, 1, [Some(Answer::LastWord(0)), Some(Answer::Labelled("Sum:"))])
+    },
+    solver(2022, 2, [Some(Answer::Labelled("Old score:")), Some(Answer::Labelled("New score:"))]),
+    solver(2022, 3, [Some(Answer::Labelled("rucksack_sum:")), Some(Answer::Labelled("group_sum:"))]),
+    solver(2022, 4, TWO_LINES),
+    solver(2022, 5, TWO_LINES),
+    solver(2022, 6, [Some(Answer::Labelled("First half:")), Some(Answer::Labelled("Second half:"))]),
+    solver(2022, 7, TWO_LINES),
+    solver(2022, 8, TWO_LINES),
+    solver(2022, 9, TWO_LINES),
+    solver(2022, 10, TWO_LINES),
+    solver(2022, 11, [None, Some(Answer::Line(0))]),
+    solver(2022, 12, TWO_LINES),
+    solver(2022, 13, [Some(Answer::Line(0)), Some(Answer::Labelled("decoder key:"))]),
+    // Maps, timings and debug lines are printed around the answers, which are the only lines holding just a number.
+    // Day 14 prints a map between its two answers, so counting from the end found a line of that map for part 1.
+    solver(2022, 14, [Some(Answer::Number(0)), Some(Answer::Number(1))]),
+    solver(2022, 15, [Some(Answer::Number(0)), Some(Answer::Number(1))]),
+    solver(2022, 16, [None, None]),
+    solver(2023, 1, SOLUTION_LABELS),
+    solver(2023, 2, SOLUTION_LABELS),
+    solver(2023, 3, SOLUTION_LABELS),
+    solver(2023, 4, SOLUTION_LABELS),
+    solver(2023, 5, SOLUTION_LABELS),
+    solver(2023, 6, SOLUTION_LABELS),
+];
+
+pub fn find_solver(year: u32, day: u32) -> Option<&'static Solver> {
+    SOLVERS.iter().find(|s| s.year == year && s.day == day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::{list_days, repo_root};
+    use crate::runner::answer_lines;
+
+    #[test]
+    fn test_every_day_is_registered() {
+        for day in list_days(&repo_root()).unwrap() {
+            let solver = find_solver(day.year, day.day);
+            assert!(solver.is_some(), "{} has no solver registered", day.name());
+
+            let solver = solver.unwrap();
+            if solver.parts.iter().any(|part| part.is_some()) {
+                assert!(day.path.join(solver.input).is_file(), "{} has no input", day.name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let lines = answer_lines("69149 70586 72070\nSum: 211805\n1 took: 0s\nSolution:\n\t56506\nSolution:\n\t56017\n");
+        let map_lines = answer_lines("  0: ..*..\n  1: .#.#.\n24\n  0: ..*..\n  1: #####\n93\n");
+
+        assert_eq!(Some("72070".to_string()), Answer::LastWord(0).extract(&lines));
+        assert_eq!(Some("211805".to_string()), Answer::Labelled("Sum:").extract(&lines));
+        assert_eq!(Some("56017".to_string()), Answer::AfterLabel("Solution:", 1).extract(&lines));
+        assert_eq!(None, Answer::AfterLabel("Solution:", 2).extract(&lines));
+        assert_eq!(Some("Sum: 211805".to_string()), Answer::Line(1).extract(&lines));
+        assert_eq!(None, Answer::Line(10).extract(&lines));
+        assert_eq!(Some("24".to_string()), Answer::Number(0).extract(&map_lines));
+        assert_eq!(Some("93".to_string()), Answer::Number(1).extract(&map_lines));
+        assert_eq!(Some("56017".to_string()), Answer::Number(1).extract(&lines));
+    }
+
+    #[test]
+    fn test_part() {
+        let solver = find_solver(2022, 11).unwrap();
+
+        assert_eq!(None, solver.part(0));
+        assert_eq!(None, solver.part(1));
+        assert_eq!(Some(Answer::Line(0)), solver.part(2));
+        assert_eq!(None, solver.part(3));
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+
+use crate::days::{list_days, Day};
+use crate::registry::find_solver;
+use crate::runner::run_solution_with_timeout;
+
+/// Answers accepted on the Advent of Code site, by day name and part, e.g. `"2022/day04" -> "1" -> "424"`.
+pub type VerifiedAnswers = BTreeMap<String, BTreeMap<String, String>>;
+
+pub fn answers_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("answers.json")
+}
+
+pub fn load_verified_answers(path: &Path) -> Result<VerifiedAnswers> {
+    if !path.is_file() {
+        return Ok(VerifiedAnswers::new());
+    }
+
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+pub fn save_verified_answers(path: &Path, answers: &VerifiedAnswers) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(answers)? + "\n")?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartStatus {
+    /// No solution is registered for the part
+    Missing,
+    Solved(String),
+    /// The day did not build or run, or its output had no answer for the part
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Verified,
+    /// The answer differs from the verified one, which is kept here
+    Mismatch(String),
+    Unverified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartReport {
+    pub part: usize,
+    pub status: PartStatus,
+    pub verification: Verification,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Ran(Duration),
+    TimedOut(Duration),
+    Failed,
+    BuildFailed,
+    /// Nothing to run, none of the parts are solved
+    Skipped,
+}
+
+/// Rendered output recorded by a day's snapshot tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visualization {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DayReport {
+    pub name: String,
+    pub run: RunStatus,
+    pub parts: Vec<PartReport>,
+    pub visualizations: Vec<Visualization>,
+}
+
+fn verify(verified: Option<&String>, status: &PartStatus) -> Verification {
+    match (verified, status) {
+        (Some(verified), PartStatus::Solved(answer)) if verified == answer => Verification::Verified,
+        (Some(verified), PartStatus::Solved(_)) => Verification::Mismatch(verified.clone()),
+        _ => Verification::Unverified,
+    }
+}
+
+/// Reads the snapshots stored by insta, dropping their metadata header.
+fn load_visualizations(day: &Day) -> Result<Vec<Visualization>> {
+    let dir = day.path.join("src").join("snapshots");
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "snap"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let text = fs::read_to_string(path)?;
+            let content = text.splitn(3, "---\n").nth(2).unwrap_or("").trim_end().to_string();
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            let name = stem.rsplit("__").next().unwrap_or(&stem).to_string();
+
+            Ok(Visualization { name, content })
+        })
+        .collect()
+}
+
+fn report_day(day: &Day, verified: &VerifiedAnswers, timeout: Option<Duration>) -> Result<DayReport> {
+    let solver = find_solver(day.year, day.day);
+    let answers = solver.map(|solver| solver.parts).unwrap_or([None, None]);
+    let no_verified_answers = BTreeMap::new();
+    let verified = verified.get(&day.name()).unwrap_or(&no_verified_answers);
+
+    let (run, lines) = if answers.iter().all(|answer| answer.is_none()) {
+        (RunStatus::Skipped, vec![])
+    } else {
+        let solution = run_solution_with_timeout(day, timeout)?;
+
+        match solution.run {
+            None => (RunStatus::BuildFailed, vec![]),
+            Some(run) if run.timed_out => (RunStatus::TimedOut(run.duration), vec![]),
+            Some(run) if !run.success => (RunStatus::Failed, vec![]),
+            Some(run) => (RunStatus::Ran(run.duration), run.answers()),
+        }
+    };
+
+    let parts = answers
+        .iter()
+        .enumerate()
+        .map(|(index, answer)| {
+            let status = match answer {
+                None => PartStatus::Missing,
+                Some(answer) => answer.extract(&lines).map_or(PartStatus::Failed, PartStatus::Solved),
+            };
+            let part = index + 1;
+            let verification = verify(verified.get(&part.to_string()), &status);
+
+            PartReport { part, status, verification }
+        })
+        .collect();
+
+    Ok(DayReport {
+        name: day.name(),
+        run,
+        parts,
+        visualizations: load_visualizations(day)?,
+    })
+}
+
+/// Runs every day, or only the ones of the given year, and collects their status.
+pub fn build_report(
+    root: &Path,
+    year: Option<u32>,
+    verified: &VerifiedAnswers,
+    timeout: Option<Duration>,
+) -> Result<Vec<DayReport>> {
+    list_days(root)?
+        .iter()
+        .filter(|day| year.is_none_or(|year| day.year == year))
+        .map(|day| {
+            eprintln!("Running {}", day.name());
+            report_day(day, verified, timeout)
+        })
+        .collect()
+}
+
+/// Records an answer the Advent of Code site accepted, entered by hand since a run can't know it is right.
+/// Returns the verified answer it replaces, if any.
+pub fn verify_answer(verified: &mut VerifiedAnswers, day: &Day, part: u32, answer: &str) -> Result<Option<String>> {
+    if !(1..=2).contains(&part) {
+        bail!("Days only have parts 1 and 2, not {}", part);
+    }
+    let answer = answer.trim();
+    if answer.is_empty() {
+        bail!("The answer to verify is empty");
+    }
+
+    Ok(verified.entry(day.name()).or_default().insert(part.to_string(), answer.to_string()))
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() > 0 {
+        format!("{:.2} s", duration.as_secs_f64())
+    } else {
+        format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+    }
+}
+
+fn run_summary(run: &RunStatus) -> String {
+    match run {
+        RunStatus::Ran(duration) => format_duration(*duration),
+        RunStatus::TimedOut(duration) => format!("timed out after {}", format_duration(*duration)),
+        RunStatus::Failed => "failed".to_string(),
+        RunStatus::BuildFailed => "build failed".to_string(),
+        RunStatus::Skipped => "-".to_string(),
+    }
+}
+
+fn verification_mark(verification: &Verification) -> &'static str {
+    match verification {
+        Verification::Verified => "✅",
+        Verification::Mismatch(_) => "❌",
+        Verification::Unverified => "❔",
+    }
+}
+
+fn is_multiline(answer: &str) -> bool {
+    answer.contains('\n')
+}
+
+pub fn render_markdown(reports: &[DayReport]) -> String {
+    let mut md = String::from("# Advent of Code progress\n\n");
+    md += "✅ verified answer, ❔ unverified answer, ❌ differs from the verified answer, ⚠️ failed, - not solved\n\n";
+    md += "| Day | Part 1 | Part 2 | Run time |\n";
+    md += "| --- | --- | --- | --- |\n";
+
+    for report in reports {
+        let cells: Vec<String> = report
+            .parts
+            .iter()
+            .map(|part| match (&part.status, &part.verification) {
+                (PartStatus::Missing, _) => "-".to_string(),
+                (PartStatus::Failed, _) => "⚠️".to_string(),
+                (PartStatus::Solved(answer), verification) => {
+                    let mut cell = if is_multiline(answer) {
+                        format!("{} (see below)", verification_mark(verification))
+                    } else {
+                        format!("{} `{}`", verification_mark(verification), answer)
+                    };
+                    if let Verification::Mismatch(expected) = verification {
+                        cell += &format!(", expected `{}`", expected.replace('\n', " "));
+                    }
+                    cell
+                }
+            })
+            .collect();
+
+        md += &format!("| {} | {} | {} |\n", report.name, cells.join(" | "), run_summary(&report.run));
+    }
+
+    for report in reports {
+        let multiline_answers: Vec<(usize, &String)> = report
+            .parts
+            .iter()
+            .filter_map(|part| match &part.status {
+                PartStatus::Solved(answer) if is_multiline(answer) => Some((part.part, answer)),
+                _ => None,
+            })
+            .collect();
+
+        if multiline_answers.is_empty() && report.visualizations.is_empty() {
+            continue;
+        }
+
+        md += &format!("\n## {}\n", report.name);
+        for (part, answer) in multiline_answers {
+            md += &format!("\n### Part {} answer\n\n```text\n{}\n```\n", part, answer);
+        }
+        for visualization in &report.visualizations {
+            md += &format!("\n### {}\n\n```text\n{}\n```\n", visualization.name, visualization.content);
+        }
+    }
+
+    md
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn render_html(reports: &[DayReport]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Advent of Code progress</title>\n\
+         <style>\ntable { border-collapse: collapse; }\ntd, th { border: 1px solid #999; padding: 4px 8px; }\n\
+         pre { line-height: 1; }\n</style>\n</head>\n<body>\n<h1>Advent of Code progress</h1>\n",
+    );
+    html += "<p>✅ verified answer, ❔ unverified answer, ❌ differs from the verified answer, ⚠️ failed, - not solved</p>\n";
+    html += "<table>\n<tr><th>Day</th><th>Part 1</th><th>Part 2</th><th>Run time</th></tr>\n";
+
+    for report in reports {
+        html += &format!("<tr><td>{}</td>", escape_html(&report.name));
+
+        for part in &report.parts {
+            let cell = match (&part.status, &part.verification) {
+                (PartStatus::Missing, _) => "-".to_string(),
+                (PartStatus::Failed, _) => "⚠️".to_string(),
+                (PartStatus::Solved(answer), verification) => {
+                    let mut cell = format!(
+                        "{} <pre>{}</pre>",
+                        verification_mark(verification),
+                        escape_html(answer)
+                    );
+                    if let Verification::Mismatch(expected) = verification {
+                        cell += &format!("expected <pre>{}</pre>", escape_html(expected));
+                    }
+                    cell
+                }
+            };
+            html += &format!("<td>{}</td>", cell);
+        }
+
+        html += &format!("<td>{}</td></tr>\n", escape_html(&run_summary(&report.run)));
+    }
+    html += "</table>\n";
+
+    for report in reports.iter().filter(|report| !report.visualizations.is_empty()) {
+        html += &format!("<h2>{}</h2>\n", escape_html(&report.name));
+        for visualization in &report.visualizations {
+            html += &format!(
+                "<h3>{}</h3>\n<pre>{}</pre>\n",
+                escape_html(&visualization.name),
+                escape_html(&visualization.content)
+            );
+        }
+    }
+
+    html + "</body>\n</html>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::{find_day, repo_root};
+
+    fn part(part: usize, status: PartStatus, verification: Verification) -> PartReport {
+        PartReport { part, status, verification }
+    }
+
+    fn reports() -> Vec<DayReport> {
+        vec![
+            DayReport {
+                name: "2022/day04".to_string(),
+                run: RunStatus::Ran(Duration::from_micros(1500)),
+                parts: vec![
+                    part(1, PartStatus::Solved("424".to_string()), Verification::Verified),
+                    part(2, PartStatus::Solved("805".to_string()), Verification::Mismatch("804".to_string())),
+                ],
+                visualizations: vec![],
+            },
+            DayReport {
+                name: "2022/day10".to_string(),
+                run: RunStatus::TimedOut(Duration::from_secs(2)),
+                parts: vec![
+                    part(1, PartStatus::Failed, Verification::Unverified),
+                    part(2, PartStatus::Solved("#.\n.#".to_string()), Verification::Unverified),
+                ],
+                visualizations: vec![Visualization { name: "screen".to_string(), content: "<#>".to_string() }],
+            },
+            DayReport {
+                name: "2022/day16".to_string(),
+                run: RunStatus::Skipped,
+                parts: vec![
+                    part(1, PartStatus::Missing, Verification::Unverified),
+                    part(2, PartStatus::Missing, Verification::Unverified),
+                ],
+                visualizations: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_verify() {
+        let solved = PartStatus::Solved("424".to_string());
+
+        assert_eq!(Verification::Verified, verify(Some(&"424".to_string()), &solved));
+        assert_eq!(Verification::Mismatch("42".to_string()), verify(Some(&"42".to_string()), &solved));
+        assert_eq!(Verification::Unverified, verify(None, &solved));
+        assert_eq!(Verification::Unverified, verify(Some(&"42".to_string()), &PartStatus::Failed));
+    }
+
+    #[test]
+    fn test_load_visualizations() {
+        let day = find_day(&repo_root(), 2022, 10).unwrap();
+        let visualizations = load_visualizations(&day).unwrap();
+
+        assert_eq!(1, visualizations.len());
+        assert_eq!("test_input_display", visualizations[0].name);
+        assert!(visualizations[0].content.starts_with("##..##"));
+        assert_eq!(6, visualizations[0].content.lines().count());
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let md = render_markdown(&reports());
+
+        assert!(md.contains("| 2022/day04 | ✅ `424` | ❌ `805`, expected `804` | 1.5 ms |"));
+        assert!(md.contains("| 2022/day10 | ⚠️ | ❔ (see below) | timed out after 2.00 s |"));
+        assert!(md.contains("| 2022/day16 | - | - | - |"));
+        assert!(md.contains("## 2022/day10\n\n### Part 2 answer\n\n```text\n#.\n.#\n```\n"));
+        assert!(md.contains("### screen\n\n```text\n<#>\n```\n"));
+        assert!(!md.contains("## 2022/day04"));
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html(&reports());
+
+        assert!(html.contains("<tr><td>2022/day04</td><td>✅ <pre>424</pre></td>"));
+        assert!(html.contains("<h3>screen</h3>\n<pre>&lt;#&gt;</pre>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_verify_answer() {
+        let mut verified = VerifiedAnswers::new();
+        let day = Day { year: 2022, day: 10, path: PathBuf::new() };
+
+        assert_eq!(None, verify_answer(&mut verified, &day, 2, " ELPLZGZL\n").unwrap());
+        assert_eq!(Some(&"ELPLZGZL".to_string()), verified["2022/day10"].get("2"));
+        assert_eq!(Some("ELPLZGZL".to_string()), verify_answer(&mut verified, &day, 2, "ELPLZGZF").unwrap());
+
+        assert!(verify_answer(&mut verified, &day, 3, "1").is_err());
+        assert!(verify_answer(&mut verified, &day, 1, " ").is_err());
+        assert_eq!(1, verified["2022/day10"].len());
+    }
+
+    #[test]
+    fn test_verified_answers_round_trip() {
+        let path = std::env::temp_dir().join(format!("aoc-answers-{}.json", std::process::id()));
+        let mut verified = VerifiedAnswers::new();
+        verified.entry("2022/day04".to_string()).or_default().insert("1".to_string(), "424".to_string());
+
+        save_verified_answers(&path, &verified).unwrap();
+        assert_eq!(verified, load_verified_answers(&path).unwrap());
+
+        fs::remove_file(&path).unwrap();
+        assert!(load_verified_answers(&path).unwrap().is_empty());
+    }
+}
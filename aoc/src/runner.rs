@@ -0,0 +1,210 @@
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::days::Day;
+
+// Guards the scratch folders used for running days on other inputs
+static SCRATCH_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone)]
+pub struct RunOutput {
+    pub success: bool,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+impl RunOutput {
+    /// Non empty lines of the output, which for the solutions are the printed answers.
+    pub fn answers(&self) -> Vec<String> {
+        answer_lines(&self.stdout)
+    }
+}
+
+/// Release build of a day followed by running it, `run` is None if the build failed.
+#[derive(Debug, Clone)]
+pub struct SolutionRun {
+    pub build: RunOutput,
+    pub run: Option<RunOutput>,
+}
+
+fn read_to_end(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        let _ = pipe.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).to_string()
+    })
+}
+
+/// Runs the command to completion, or kills it once the timeout passes.
+fn run_command_with_timeout(command: &mut Command, timeout: Option<Duration>) -> Result<RunOutput> {
+    let start = Instant::now();
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run {:?}", command.get_program()))?;
+
+    // Pipes are drained on their own threads so a chatty child can't block on a full pipe
+    let stdout = read_to_end(child.stdout.take().unwrap());
+    let stderr = read_to_end(child.stderr.take().unwrap());
+    let mut timed_out = false;
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if timeout.is_some_and(|timeout| start.elapsed() > timeout) {
+            timed_out = true;
+            child.kill()?;
+            break child.wait()?;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    };
+    let duration = start.elapsed();
+
+    Ok(RunOutput {
+        success: status.success() && !timed_out,
+        timed_out,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        duration,
+    })
+}
+
+fn run_command(command: &mut Command) -> Result<RunOutput> {
+    run_command_with_timeout(command, None)
+}
+
+/// Runs the day's tests, which check the examples from the puzzle description.
+pub fn run_tests(day: &Day) -> Result<RunOutput> {
+    run_command(Command::new("cargo").args(["test", "--quiet"]).current_dir(&day.path))
+}
+
+fn build_and_run(crate_dir: &Path, target_dir: &Path, timeout: Option<Duration>) -> Result<SolutionRun> {
+    let build = run_command(
+        Command::new("cargo")
+            .args(["build", "--release", "--quiet"])
+            .env("CARGO_TARGET_DIR", target_dir)
+            .current_dir(crate_dir),
+    )?;
+    if !build.success {
+        return Ok(SolutionRun { build, run: None });
+    }
+
+    // Binaries are named after their folder, run it directly so the timing excludes cargo
+    let name = crate_dir
+        .file_name()
+        .ok_or(anyhow!("Crate folder {} has no name", crate_dir.display()))?;
+    let binary = target_dir.join("release").join(name);
+    let run = run_command_with_timeout(Command::new(binary).current_dir(crate_dir), timeout)?;
+
+    Ok(SolutionRun { build, run: Some(run) })
+}
+
+/// Builds the day in release mode and runs it on the real input.
+pub fn run_solution(day: &Day) -> Result<SolutionRun> {
+    run_solution_with_timeout(day, None)
+}
+
+/// Like `run_solution`, but kills the solution if it runs longer than the timeout.
+pub fn run_solution_with_timeout(day: &Day, timeout: Option<Duration>) -> Result<SolutionRun> {
+    build_and_run(&day.path, &day.path.join("target"), timeout)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let dest = to.join(path.file_name().unwrap());
+
+        if path.is_dir() {
+            copy_dir(&path, &dest)?;
+        } else {
+            fs::copy(&path, &dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds and runs the day on the given input instead of its own.
+///
+/// The solutions embed their input at compile time, so the crate is copied to a scratch folder with the
+/// input replaced. Builds share a target folder per year, so only the day itself gets recompiled.
+pub fn run_solution_on_input(day: &Day, input_file: &str, input: &str) -> Result<SolutionRun> {
+    let _guard = SCRATCH_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let scratch = env::temp_dir().join("aoc-runner").join(day.year.to_string());
+    let crate_dir = scratch.join(day.path.file_name().unwrap());
+
+    if crate_dir.exists() {
+        fs::remove_dir_all(&crate_dir)?;
+    }
+    copy_dir(&day.path.join("src"), &crate_dir.join("src"))?;
+    for file in ["Cargo.toml", "Cargo.lock"] {
+        if day.path.join(file).is_file() {
+            fs::copy(day.path.join(file), crate_dir.join(file))?;
+        }
+    }
+    fs::write(crate_dir.join(input_file), input)?;
+
+    build_and_run(&crate_dir, &scratch.join("target"), None)
+}
+
+pub fn answer_lines(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::{find_day, repo_root};
+
+    #[test]
+    fn test_answer_lines() {
+        let output = "\n14780\n\tSolution:\n  42  \n\n";
+
+        assert_eq!(vec!["14780", "Solution:", "42"], answer_lines(output));
+    }
+
+    #[test]
+    fn test_run_command_with_timeout() {
+        let output = run_command_with_timeout(Command::new("sleep").arg("5"), Some(Duration::from_millis(50))).unwrap();
+
+        assert!(output.timed_out);
+        assert!(!output.success);
+        assert!(output.duration < Duration::from_secs(5));
+
+        let output = run_command_with_timeout(Command::new("echo").arg("42"), Some(Duration::from_secs(5))).unwrap();
+
+        assert!(!output.timed_out);
+        assert_eq!(vec!["42"], output.answers());
+    }
+
+    #[test]
+    fn test_run_solution_on_input() {
+        let day = find_day(&repo_root(), 2022, 12).unwrap();
+        let input = fs::read_to_string(day.path.join("src/test_input.txt")).unwrap();
+        let solution = run_solution_on_input(&day, "src/input.txt", &input).unwrap();
+
+        assert!(solution.build.success);
+        assert_eq!(vec!["31", "29"], solution.run.unwrap().answers());
+    }
+}
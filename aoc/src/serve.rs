@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::days::{find_day, list_days};
+use crate::registry::{find_solver, SOLVERS};
+use crate::runner::{run_solution, run_solution_on_input};
+
+#[derive(Debug, PartialEq, Eq)]
+enum Route {
+    Health,
+    Solve { year: u32, day: u32, part: usize },
+    NotFound,
+}
+
+fn route(method: &Method, url: &str) -> Route {
+    let path = url.split('?').next().unwrap_or("");
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["health"]) => Route::Health,
+        (Method::Post, ["solve", year, day, part]) => match (year.parse(), day.parse(), part.parse()) {
+            (Ok(year), Ok(day), Ok(part)) => Route::Solve { year, day, part },
+            _ => Route::NotFound,
+        },
+        _ => Route::NotFound,
+    }
+}
+
+fn error(status: u16, message: String) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+fn health(root: &Path) -> Result<Value> {
+    let days: Vec<Value> = list_days(root)?
+        .iter()
+        .filter_map(|day| SOLVERS.iter().find(|solver| solver.matches(day)))
+        .map(|solver| {
+            let parts: Vec<usize> = (1..=2).filter(|&part| solver.part(part).is_some()).collect();
+            json!({ "year": solver.year, "day": solver.day, "parts": parts })
+        })
+        .collect();
+
+    Ok(json!({ "status": "ok", "days": days }))
+}
+
+/// Solves the part on the given input, or on the day's own input when it's empty.
+fn solve(root: &Path, year: u32, day: u32, part: usize, input: &str) -> Result<(u16, Value)> {
+    let Some(answer) = find_solver(year, day).and_then(|solver| solver.part(part).map(|answer| (solver, answer)))
+    else {
+        return Ok(error(404, format!("No solver registered for {}/day{:02} part {}", year, day, part)));
+    };
+    let (solver, answer) = answer;
+    let day = find_day(root, year, day)?;
+
+    let solution = if input.trim().is_empty() {
+        run_solution(&day)?
+    } else {
+        run_solution_on_input(&day, solver.input, input)?
+    };
+
+    let run = match solution.run {
+        Some(run) if run.success => run,
+        Some(run) => return Ok(error(422, format!("Solution failed: {}", run.stderr.trim()))),
+        None => return Ok(error(500, format!("Build failed: {}", solution.build.stderr.trim()))),
+    };
+
+    let Some(answer) = answer.extract(&run.answers()) else {
+        return Ok(error(422, "Could not find the answer in the solution's output".to_string()));
+    };
+
+    Ok((
+        200,
+        json!({
+            "year": year,
+            "day": day.day,
+            "part": part,
+            "answer": answer,
+            "timings": {
+                "build_ms": solution.build.duration.as_secs_f64() * 1000.0,
+                "run_ms": run.duration.as_secs_f64() * 1000.0,
+            },
+        }),
+    ))
+}
+
+fn handle(root: &Path, method: &Method, url: &str, body: &str) -> (u16, Value) {
+    let result = match route(method, url) {
+        Route::Health => health(root).map(|value| (200, value)),
+        Route::Solve { year, day, part } => solve(root, year, day, part, body),
+        Route::NotFound => Ok(error(404, format!("No route for {} {}", method, url))),
+    };
+
+    result.unwrap_or_else(|err| error(500, err.to_string()))
+}
+
+/// Serves the solvers over HTTP on localhost, requests are handled one at a time.
+pub fn serve(root: &Path, port: u16) -> Result<()> {
+    let server = Server::http(("127.0.0.1", port)).map_err(|err| anyhow!(err))?;
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    println!("Listening on http://127.0.0.1:{}", port);
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, value) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle(root, request.method(), request.url(), &body),
+            Err(err) => error(400, format!("Could not read request body: {}", err)),
+        };
+
+        println!("{} {} -> {}", request.method(), request.url(), status);
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+
+        if let Err(err) = request.respond(response) {
+            eprintln!("Could not send response: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::days::repo_root;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(Route::Health, route(&Method::Get, "/health"));
+        assert_eq!(Route::Solve { year: 2022, day: 4, part: 2 }, route(&Method::Post, "/solve/2022/4/2"));
+        assert_eq!(Route::Solve { year: 2023, day: 1, part: 1 }, route(&Method::Post, "/solve/2023/01/1?x=1"));
+        assert_eq!(Route::NotFound, route(&Method::Get, "/solve/2022/4/2"));
+        assert_eq!(Route::NotFound, route(&Method::Post, "/solve/2022/four/2"));
+        assert_eq!(Route::NotFound, route(&Method::Post, "/solve/2022/4"));
+    }
+
+    #[test]
+    fn test_health() {
+        let (status, value) = handle(&repo_root(), &Method::Get, "/health", "");
+        let days = value["days"].as_array().unwrap();
+
+        assert_eq!(200, status);
+        assert!(days.contains(&json!({ "year": 2022, "day": 11, "parts": [2] })));
+        assert!(days.contains(&json!({ "year": 2022, "day": 16, "parts": [] })));
+    }
+
+    #[test]
+    fn test_solve() {
+        let input = include_str!("../../2022/day12/src/test_input.txt");
+        let (status, value) = handle(&repo_root(), &Method::Post, "/solve/2022/12/2", input);
+
+        assert_eq!(200, status, "{}", value);
+        assert_eq!("29", value["answer"]);
+        assert!(value["timings"]["run_ms"].as_f64().is_some());
+    }
+
+    #[test]
+    fn test_solve_unknown() {
+        assert_eq!(404, handle(&repo_root(), &Method::Post, "/solve/2022/16/1", "").0);
+        assert_eq!(404, handle(&repo_root(), &Method::Post, "/solve/2022/12/3", "").0);
+        assert_eq!(404, handle(&repo_root(), &Method::Post, "/solve/2019/1/1", "").0);
+    }
+}
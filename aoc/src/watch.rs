@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use similar::{ChangeTag, TextDiff};
+
+use crate::days::Day;
+use crate::runner::{run_solution, run_tests, RunOutput};
+
+/// Latest modification time and file count of the watched files, a change in either triggers a rerun.
+type Snapshot = (Option<SystemTime>, usize);
+
+/// Snapshots insta writes when a snapshot test fails, watching them would rerun the failing tests forever.
+fn is_pending_snapshot(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "pending-snap")
+        || path.to_string_lossy().ends_with(".snap.new")
+}
+
+fn collect_files(path: &Path, snapshot: &mut Snapshot) -> Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), snapshot)?;
+        }
+    } else if path.is_file() && !is_pending_snapshot(path) {
+        let modified = fs::metadata(path)?.modified()?;
+        snapshot.0 = snapshot.0.max(Some(modified));
+        snapshot.1 += 1;
+    }
+
+    Ok(())
+}
+
+// Sources, inputs and accepted snapshots all live in src/, target/ is skipped since every build changes it
+fn watched_snapshot(day: &Day) -> Result<Snapshot> {
+    let mut snapshot = (None, 0);
+
+    collect_files(&day.path.join("Cargo.toml"), &mut snapshot)?;
+    collect_files(&day.path.join("src"), &mut snapshot)?;
+
+    Ok(snapshot)
+}
+
+/// Compact diff between the answers of two runs, only changed lines are listed.
+fn answer_diff(old: &[String], new: &[String]) -> Vec<String> {
+    let old = old.join("\n") + "\n";
+    let new = new.join("\n") + "\n";
+
+    TextDiff::from_lines(&old, &new)
+        .iter_all_changes()
+        .filter_map(|change| match change.tag() {
+            ChangeTag::Delete => Some(format!("- {}", change.value().trim_end())),
+            ChangeTag::Insert => Some(format!("+ {}", change.value().trim_end())),
+            ChangeTag::Equal => None,
+        })
+        .collect()
+}
+
+fn print_failure(what: &str, output: &RunOutput) {
+    println!("{} failed after {:.2?}", what, output.duration);
+    for line in output.stdout.lines().chain(output.stderr.lines()) {
+        println!("    {}", line);
+    }
+}
+
+fn run_once(day: &Day, previous_answers: &mut Option<Vec<String>>) -> Result<()> {
+    println!("==> {}", day.name());
+
+    let tests = run_tests(day)?;
+    if tests.success {
+        let summary = tests
+            .stdout
+            .lines()
+            .filter(|line| line.starts_with("test result"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("tests passed in {:.2?} ({})", tests.duration, summary);
+    } else {
+        print_failure("tests", &tests);
+    }
+
+    let solution = run_solution(day)?;
+    let run = match solution.run {
+        Some(run) if run.success => run,
+        Some(run) => {
+            print_failure("solution", &run);
+            return Ok(());
+        }
+        None => {
+            print_failure("build", &solution.build);
+            return Ok(());
+        }
+    };
+
+    let answers = run.answers();
+    println!("solution built in {:.2?}, ran in {:.2?}", solution.build.duration, run.duration);
+
+    match previous_answers {
+        Some(previous) if *previous == answers => println!("answers unchanged"),
+        Some(previous) => {
+            for line in answer_diff(previous, &answers) {
+                println!("    {}", line);
+            }
+        }
+        None => {
+            for line in &answers {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    *previous_answers = Some(answers);
+    Ok(())
+}
+
+/// Reruns the day's tests and solution every time its sources or inputs change, never returns on success.
+pub fn watch(day: &Day, poll_interval: Duration) -> Result<()> {
+    let mut last_snapshot = None;
+    let mut previous_answers = None;
+
+    loop {
+        let snapshot = watched_snapshot(day)?;
+
+        if last_snapshot != Some(snapshot) {
+            last_snapshot = Some(snapshot);
+            run_once(day, &mut previous_answers)?;
+            println!();
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_answer_diff() {
+        let old = lines(&["13140", "##..##", "####.."]);
+        let new = lines(&["13140", "##..##", "###..."]);
+
+        assert_eq!(vec!["- ####..", "+ ###..."], answer_diff(&old, &new));
+        assert!(answer_diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_watched_snapshot_changes_with_files() {
+        let path = std::env::temp_dir().join(format!("aoc-watch-{}", std::process::id()));
+        fs::create_dir_all(path.join("src/snapshots")).unwrap();
+        fs::write(path.join("Cargo.toml"), "[package]").unwrap();
+        fs::write(path.join("src/main.rs"), "fn main() {}").unwrap();
+        let day = Day { year: 2022, day: 1, path: path.clone() };
+
+        let snapshot = watched_snapshot(&day).unwrap();
+        assert!(snapshot.0.is_some());
+        assert_eq!(2, snapshot.1);
+
+        // Failing snapshot tests must not trigger a rerun
+        fs::write(path.join("src/snapshots/day01__tests__map.snap.new"), "new").unwrap();
+        fs::write(path.join("src/snapshots/.day01__tests__map.pending-snap"), "new").unwrap();
+        assert_eq!(snapshot, watched_snapshot(&day).unwrap());
+
+        fs::write(path.join("src/snapshots/day01__tests__map.snap"), "accepted").unwrap();
+        let added = watched_snapshot(&day).unwrap();
+        assert_eq!(3, added.1);
+
+        let later = snapshot.0.unwrap() + Duration::from_secs(60);
+        fs::File::options().write(true).open(path.join("src/main.rs")).unwrap().set_modified(later).unwrap();
+        let touched = watched_snapshot(&day).unwrap();
+        assert_eq!(Some(later), touched.0);
+        assert_ne!(added, touched);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}